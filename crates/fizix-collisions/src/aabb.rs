@@ -0,0 +1,95 @@
+use fizix_core::Precision;
+use nalgebra::{Isometry3, Point3, Vector3};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3<Precision>,
+    pub max: Point3<Precision>
+}
+
+impl Aabb {
+    pub const EMPTY: Self = Self {
+        min: Point3::new(Precision::INFINITY, Precision::INFINITY, Precision::INFINITY),
+        max: Point3::new(Precision::NEG_INFINITY, Precision::NEG_INFINITY, Precision::NEG_INFINITY)
+    };
+
+    #[inline]
+    pub fn new(min: Point3<Precision>, max: Point3<Precision>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Point3<Precision>>) -> Self {
+        let mut aabb = Self::EMPTY;
+
+        for point in points {
+            aabb.include_point(point);
+        }
+
+        aabb
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    #[inline]
+    pub fn center(&self) -> Point3<Precision> {
+        nalgebra::center(&self.min, &self.max)
+    }
+
+    #[inline]
+    pub fn half_extents(&self) -> Vector3<Precision> {
+        (self.max - self.min) * 0.5
+    }
+
+    #[inline]
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x &&
+        self.min.y <= other.max.y && self.max.y >= other.min.y &&
+        self.min.z <= other.max.z && self.max.z >= other.min.z
+    }
+
+    #[inline]
+    pub fn contains_point(&self, point: &Point3<Precision>) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x &&
+        point.y >= self.min.y && point.y <= self.max.y &&
+        point.z >= self.min.z && point.z <= self.max.z
+    }
+
+    // the point itself when it is inside
+    #[inline]
+    pub fn closest_point(&self, point: &Point3<Precision>) -> Point3<Precision> {
+        point.sup(&self.min).inf(&self.max)
+    }
+
+    #[inline]
+    pub fn include_point(&mut self, point: &Point3<Precision>) {
+        self.min = self.min.inf(point);
+        self.max = self.max.sup(point);
+    }
+
+    #[inline]
+    pub fn merged(&self, other: &Aabb) -> Self {
+        Self::new(self.min.inf(&other.min), self.max.sup(&other.max))
+    }
+
+    #[inline]
+    pub fn loosened(&self, margin: Precision) -> Self {
+        let margin = Vector3::repeat(margin);
+
+        Self::new(self.min - margin, self.max + margin)
+    }
+
+    pub fn transform_by(&self, pose: &Isometry3<Precision>) -> Self {
+        if self.is_empty() { return *self; }
+
+        // bounds of a rotated box come from the absolute rotation matrix
+        let rotation = pose.rotation.to_rotation_matrix().into_inner().abs();
+
+        let center = pose * self.center();
+        let half_extents = rotation * self.half_extents();
+
+        Self::new(center - half_extents, center + half_extents)
+    }
+}
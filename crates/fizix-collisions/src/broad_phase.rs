@@ -0,0 +1,155 @@
+use std::ops::Deref;
+
+use crate::Aabb;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ProxyId(usize);
+
+impl ProxyId {
+    pub const INVALID: Self = Self(usize::MAX);
+
+    pub fn new(index: usize) -> Self {
+        Self(index)
+    }
+}
+
+impl Deref for ProxyId {
+    type Target = usize;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+// proxies are updated one at a time so shapes can sync incrementally
+#[derive(Default)]
+pub struct BroadPhase {
+    proxies: Vec<Option<Aabb>>,
+    free_list: Vec<usize>
+}
+
+impl BroadPhase {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, aabb: Aabb) -> ProxyId {
+        if let Some(index) = self.free_list.pop() {
+            self.proxies[index] = Some(aabb);
+
+            return ProxyId::new(index);
+        }
+
+        self.proxies.push(Some(aabb));
+
+        ProxyId::new(self.proxies.len() - 1)
+    }
+
+    pub fn update(&mut self, proxy: ProxyId, aabb: Aabb) {
+        if let Some(slot) = self.proxies.get_mut(*proxy).and_then(Option::as_mut) {
+            *slot = aabb;
+        }
+    }
+
+    pub fn remove(&mut self, proxy: ProxyId) {
+        if let Some(slot) = self.proxies.get_mut(*proxy) && slot.take().is_some() {
+            self.free_list.push(*proxy);
+        }
+    }
+
+    #[inline]
+    pub fn aabb(&self, proxy: ProxyId) -> Option<&Aabb> {
+        self.proxies.get(*proxy).and_then(Option::as_ref)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.proxies.len() - self.free_list.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn query(&self, aabb: &Aabb) -> Vec<ProxyId> {
+        self.proxies.iter()
+            .enumerate()
+            .filter_map(|(i, proxy)| match proxy {
+                Some(other) if other.intersects(aabb) => Some(ProxyId::new(i)),
+                _ => None
+            })
+            .collect()
+    }
+
+    // sweep and prune along x, pairs come out sorted by proxy id
+    pub fn overlapping_pairs(&self) -> Vec<(ProxyId, ProxyId)> {
+        let mut sorted = self.proxies.iter()
+            .enumerate()
+            .filter_map(|(i, proxy)| Some((i, proxy.as_ref()?)))
+            .collect::<Vec<_>>();
+
+        sorted.sort_by(|(i, a), (j, b)| a.min.x.total_cmp(&b.min.x).then(i.cmp(j)));
+
+        let mut pairs = Vec::new();
+
+        for (k, &(i, a)) in sorted.iter().enumerate() {
+            // everything after b starts even further along x
+            for &(j, b) in sorted[k + 1..].iter().take_while(|(_, b)| b.min.x <= a.max.x) {
+                if a.intersects(b) {
+                    pairs.push((ProxyId::new(i.min(j)), ProxyId::new(i.max(j))));
+                }
+            }
+        }
+
+        pairs.sort_unstable_by_key(|(a, b)| (**a, **b));
+
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fizix_core::Precision;
+    use nalgebra::{Point3, Vector3};
+    use super::*;
+
+    #[test]
+    fn overlapping_pairs_match_brute_force() {
+        let mut broad_phase = BroadPhase::new();
+        let mut aabbs = Vec::new();
+
+        // a fixed pseudo random scatter of boxes, with one removed to leave a hole
+        let mut seed = 12345u32;
+        let mut next = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+
+            (seed >> 8) as Precision / (1 << 24) as Precision
+        };
+
+        for _ in 0..200 {
+            let min = Point3::new(next(), next(), next()) * 10.0;
+            let aabb = Aabb::new(min, min + Vector3::new(next(), next(), next()));
+
+            aabbs.push(Some(aabb));
+            broad_phase.insert(aabb);
+        }
+
+        broad_phase.remove(ProxyId::new(17));
+        aabbs[17] = None;
+
+        let mut expected = Vec::new();
+
+        for (i, a) in aabbs.iter().enumerate() {
+            for (j, b) in aabbs.iter().enumerate().skip(i + 1) {
+                if let (Some(a), Some(b)) = (a, b) && a.intersects(b) {
+                    expected.push((ProxyId::new(i), ProxyId::new(j)));
+                }
+            }
+        }
+
+        assert!(!expected.is_empty());
+        assert_eq!(broad_phase.overlapping_pairs(), expected);
+    }
+}
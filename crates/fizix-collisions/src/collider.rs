@@ -0,0 +1,275 @@
+use std::ops::Deref;
+
+use fizix_core::{BodyHandle, BodySet, Precision, RigidBodyDesc, World};
+use nalgebra::{Isometry3, Point3, Translation3};
+use crate::{project_point, Aabb, PointProjection, Shape};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ColliderHandle(usize);
+
+impl ColliderHandle {
+    pub const INVALID: Self = Self(usize::MAX);
+
+    pub fn new(index: usize) -> Self {
+        Self(index)
+    }
+}
+
+impl Deref for ColliderHandle {
+    type Target = usize;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Collider {
+    pub shape: Box<dyn Shape>,
+
+    pub body: BodyHandle, // INVALID for colliders fixed in world space
+    pub local_pose: Isometry3<Precision> // relative to the body, or the world when unattached
+}
+
+impl Collider {
+    pub fn new(shape: impl Shape + 'static) -> Self {
+        Self {
+            shape: Box::new(shape),
+
+            body: BodyHandle::INVALID,
+            local_pose: Isometry3::identity()
+        }
+    }
+
+    pub fn attached_to(mut self, body: BodyHandle) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn with_local_pose(mut self, local_pose: Isometry3<Precision>) -> Self {
+        self.local_pose = local_pose;
+        self
+    }
+
+    #[inline]
+    pub fn is_attached(&self) -> bool {
+        self.body != BodyHandle::INVALID
+    }
+
+    // None once the body it is attached to has been removed
+    pub fn world_pose(&self, bodies: &BodySet) -> Option<Isometry3<Precision>> {
+        if !self.is_attached() { return Some(self.local_pose); }
+
+        Some(bodies.pose(self.body)? * self.local_pose)
+    }
+
+    #[inline]
+    pub fn compute_aabb(&self, bodies: &BodySet) -> Option<Aabb> {
+        Some(self.shape.compute_aabb(&self.world_pose(bodies)?))
+    }
+
+    // for bodies with ccd enabled the bounds also cover where the collider ends up after dt at its current velocity,
+    // so the broadphase pairs fast bodies with thin geometry they would otherwise step over
+    // only the translation is swept, the rotation over the step stays within the current bounds
+    pub fn compute_swept_aabb(&self, bodies: &BodySet, dt: Precision) -> Option<Aabb> {
+        let pose = self.world_pose(bodies)?;
+        let aabb = self.shape.compute_aabb(&pose);
+
+        if !self.is_attached() || bodies.is_ccd_enabled(self.body) != Some(true) { return Some(aabb); }
+
+        let velocity = bodies.linear_velocity(self.body)?;
+
+        Some(aabb.merged(&self.shape.compute_aabb(&(Translation3::from(velocity * dt) * pose))))
+    }
+}
+
+// a body description together with the colliders to attach to the body once it exists
+pub struct RigidBodyBuilder {
+    pub desc: RigidBodyDesc,
+    pub colliders: Vec<Collider> // poses relative to the body
+}
+
+impl RigidBodyBuilder {
+    pub fn new(desc: RigidBodyDesc) -> Self {
+        Self { desc, colliders: Vec::new() }
+    }
+
+    pub fn with_collider(mut self, collider: Collider) -> Self {
+        self.colliders.push(collider);
+        self
+    }
+
+    // creates the body and attaches every collider to it in one go
+    pub fn build(self, world: &mut World, colliders: &mut ColliderSet) -> (BodyHandle, Vec<ColliderHandle>) {
+        let body = world.create_body(self.desc);
+        let handles = self.colliders.into_iter()
+            .map(|collider| colliders.add(collider.attached_to(body)))
+            .collect();
+
+        (body, handles)
+    }
+}
+
+// starts a builder straight from a description, RigidBodyDesc::dynamic().with_collider(..)
+pub trait WithCollider {
+    fn with_collider(self, collider: Collider) -> RigidBodyBuilder;
+}
+
+impl WithCollider for RigidBodyDesc {
+    #[inline]
+    fn with_collider(self, collider: Collider) -> RigidBodyBuilder {
+        RigidBodyBuilder::new(self).with_collider(collider)
+    }
+}
+
+#[derive(Default)]
+pub struct ColliderSet {
+    colliders: Vec<Collider>
+}
+
+impl ColliderSet {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, collider: Collider) -> ColliderHandle {
+        self.colliders.push(collider);
+
+        ColliderHandle::new(self.colliders.len() - 1)
+    }
+
+    #[inline]
+    pub fn get(&self, handle: ColliderHandle) -> Option<&Collider> {
+        self.colliders.get(*handle)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, handle: ColliderHandle) -> Option<&mut Collider> {
+        self.colliders.get_mut(*handle)
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.colliders.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.colliders.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ColliderHandle, &Collider)> {
+        self.colliders.iter().enumerate().map(|(i, collider)| (ColliderHandle::new(i), collider))
+    }
+
+    // nearest surface over every collider, a collider containing the point wins over closer ones that do not
+    // colliders of removed bodies are skipped
+    pub fn project_point(&self, bodies: &BodySet, point: &Point3<Precision>) -> Option<(ColliderHandle, PointProjection)> {
+        let mut best: Option<(Precision, ColliderHandle, PointProjection)> = None;
+
+        for (handle, collider) in self.iter() {
+            let Some(pose) = collider.world_pose(bodies) else { continue; };
+            let projection = project_point(collider.shape.as_ref(), &pose, point);
+            let distance_sq = (projection.point - point).norm_squared();
+
+            let is_better = match &best {
+                None => true,
+                Some((best_sq, _, best_projection)) => (projection.is_inside, -distance_sq) > (best_projection.is_inside, -best_sq)
+            };
+
+            if is_better {
+                best = Some((distance_sq, handle, projection));
+            }
+        }
+
+        best.map(|(_, handle, projection)| (handle, projection))
+    }
+
+    pub fn contains_point(&self, bodies: &BodySet, point: &Point3<Precision>) -> bool {
+        self.colliders.iter().any(|collider| {
+            collider.world_pose(bodies).is_some_and(|pose| project_point(collider.shape.as_ref(), &pose, point).is_inside)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+    use crate::{Ball, Cuboid};
+    use super::*;
+
+    #[test]
+    fn colliders_of_removed_bodies_have_no_pose() {
+        let mut world = World::new(Vector3::zeros(), 1, 1);
+        let mut colliders = ColliderSet::new();
+
+        let body = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(5.0, 0.0, 0.0)));
+        let attached = colliders.add(Collider::new(Ball::new(1.0)).attached_to(body));
+        let fixed = colliders.add(Collider::new(Ball::new(1.0)).with_local_pose(Isometry3::translation(0.0, 5.0, 0.0)));
+
+        world.remove_body(body);
+        world.create_body(RigidBodyDesc::dynamic());
+
+        assert_eq!(colliders.get(attached).unwrap().world_pose(world.bodies()), None);
+        assert_eq!(colliders.get(attached).unwrap().compute_aabb(world.bodies()), None);
+        assert_eq!(colliders.get(fixed).unwrap().world_pose(world.bodies()), Some(Isometry3::translation(0.0, 5.0, 0.0)));
+
+        let (handle, _) = colliders.project_point(world.bodies(), &Point3::new(5.0, 0.0, 0.0)).unwrap();
+
+        assert_eq!(handle, fixed);
+    }
+
+    #[test]
+    fn builder_attaches_its_colliders() {
+        let mut world = World::new(Vector3::zeros(), 1, 1);
+        let mut colliders = ColliderSet::new();
+
+        let (body, handles) = RigidBodyDesc::kinematic()
+            .with_position(Point3::new(0.0, 2.0, 0.0))
+            .with_collider(Collider::new(Ball::new(1.0)))
+            .with_collider(Collider::new(Ball::new(0.5)).with_local_pose(Isometry3::translation(1.0, 0.0, 0.0)))
+            .build(&mut world, &mut colliders);
+
+        assert_eq!(handles.len(), 2);
+        assert!(world.contains_body(body));
+        assert_eq!(world.bodies().body_type(body), Some(fizix_core::BodyType::Kinematic));
+
+        for handle in &handles {
+            assert_eq!(colliders.get(*handle).unwrap().body, body);
+        }
+
+        assert_eq!(colliders.get(handles[1]).unwrap().world_pose(world.bodies()), Some(Isometry3::translation(1.0, 2.0, 0.0)));
+    }
+
+    #[test]
+    fn ccd_sweeps_the_bounds_over_the_step() {
+        let mut world = World::new(Vector3::zeros(), 1, 1);
+        let mut colliders = ColliderSet::new();
+
+        let dt = 1.0 / 60.0;
+        let desc = RigidBodyDesc::dynamic().with_linear_velocity(Vector3::new(120.0, 0.0, 0.0));
+
+        let (_, fast_colliders) = desc.with_ccd(true).with_collider(Collider::new(Ball::new(0.5))).build(&mut world, &mut colliders);
+        let (_, plain_colliders) = desc.with_collider(Collider::new(Ball::new(0.5))).build(&mut world, &mut colliders);
+
+        // thin wall one metre ahead, the ball would step straight over it
+        let wall = Collider::new(Cuboid::new(Vector3::new(0.05, 5.0, 5.0))).with_local_pose(Isometry3::translation(1.5, 0.0, 0.0));
+        let wall = wall.compute_aabb(world.bodies()).unwrap();
+
+        let fast = colliders.get(fast_colliders[0]).unwrap();
+        let plain = colliders.get(plain_colliders[0]).unwrap();
+
+        let swept = fast.compute_swept_aabb(world.bodies(), dt).unwrap();
+
+        assert!((swept.min - Point3::new(-0.5, -0.5, -0.5)).norm() < 1e-6);
+        assert!((swept.max - Point3::new(2.5, 0.5, 0.5)).norm() < 1e-6);
+        assert!(swept.intersects(&wall));
+
+        assert_eq!(plain.compute_swept_aabb(world.bodies(), dt), plain.compute_aabb(world.bodies()));
+        assert!(!plain.compute_swept_aabb(world.bodies(), dt).unwrap().intersects(&wall));
+
+        world.set_body_ccd_enabled(fast.body, false);
+
+        assert_eq!(fast.compute_swept_aabb(world.bodies(), dt), fast.compute_aabb(world.bodies()));
+    }
+}
@@ -0,0 +1,106 @@
+use fizix_core::Precision;
+use nalgebra::{Isometry3, Point3};
+use crate::{project_point, Aabb, PointProjection, Shape};
+
+pub struct Compound {
+    shapes: Vec<(Isometry3<Precision>, Box<dyn Shape>)>, // poses are relative to the compound
+
+    aabb: Aabb
+}
+
+impl Compound {
+    pub fn new(shapes: Vec<(Isometry3<Precision>, Box<dyn Shape>)>) -> Self {
+        let aabb = shapes.iter()
+            .map(|(pose, shape)| shape.compute_aabb(pose))
+            .fold(Aabb::EMPTY, |a, b| a.merged(&b));
+
+        Self { shapes, aabb }
+    }
+
+    #[inline]
+    pub fn shapes(&self) -> &[(Isometry3<Precision>, Box<dyn Shape>)] {
+        &self.shapes
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+}
+
+impl Shape for Compound {
+    fn local_aabb(&self) -> Aabb {
+        self.aabb
+    }
+
+    fn project_local_point(&self, point: &Point3<Precision>) -> PointProjection {
+        let projections = self.shapes.iter()
+            .map(|(pose, shape)| project_point(shape.as_ref(), pose, point))
+            .collect::<Vec<_>>();
+
+        let is_inside = projections.iter().any(|p| p.is_inside);
+
+        // when inside, faces of the parts the point is not in are not valid exits
+        projections.into_iter()
+            .filter(|p| p.is_inside == is_inside)
+            .min_by(|p, q| (p.point - point).norm_squared().total_cmp(&(q.point - point).norm_squared()))
+            .unwrap_or(PointProjection::new(*point, false))
+    }
+
+    fn compute_aabb(&self, pose: &Isometry3<Precision>) -> Aabb {
+        // tighter than transforming the local box
+        self.shapes.iter()
+            .map(|(local_pose, shape)| shape.compute_aabb(&(pose * local_pose)))
+            .fold(Aabb::EMPTY, |a, b| a.merged(&b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fizix_core::consts::FRAC_PI_2;
+    use nalgebra::{Translation3, UnitQuaternion, Vector3};
+    use crate::{Ball, Cuboid};
+    use super::*;
+
+    fn dumbbell() -> Compound {
+        Compound::new(vec![
+            (Isometry3::translation(-2.0, 0.0, 0.0), Box::new(Ball::new(1.0))),
+            (Isometry3::translation(2.0, 0.0, 0.0), Box::new(Ball::new(1.0))),
+            (Isometry3::identity(), Box::new(Cuboid::new(Vector3::new(2.0, 0.25, 0.25))))
+        ])
+    }
+
+    #[test]
+    fn aabbs_cover_every_part() {
+        let compound = dumbbell();
+
+        assert_eq!(compound.local_aabb(), Aabb::new(Point3::new(-3.0, -1.0, -1.0), Point3::new(3.0, 1.0, 1.0)));
+
+        let pose = Isometry3::from_parts(Translation3::new(0.0, 5.0, 0.0), UnitQuaternion::from_axis_angle(&Vector3::z_axis(), FRAC_PI_2));
+        let aabb = compound.compute_aabb(&pose);
+
+        assert!((aabb.min - Point3::new(-1.0, 2.0, -1.0)).norm() < 1.0e-5);
+        assert!((aabb.max - Point3::new(1.0, 8.0, 1.0)).norm() < 1.0e-5);
+    }
+
+    #[test]
+    fn points_project_onto_the_nearest_part() {
+        let compound = dumbbell();
+
+        let outside = compound.project_local_point(&Point3::new(5.0, 0.0, 0.0));
+
+        assert!(!outside.is_inside);
+        assert!((outside.point - Point3::new(3.0, 0.0, 0.0)).norm() < 1.0e-5);
+
+        // inside the bar, the balls' surfaces are not exits
+        let inside = compound.project_local_point(&Point3::new(0.0, 0.2, 0.0));
+
+        assert!(inside.is_inside);
+        assert!((inside.point - Point3::new(0.0, 0.25, 0.0)).norm() < 1.0e-5);
+    }
+}
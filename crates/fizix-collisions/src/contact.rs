@@ -0,0 +1,10 @@
+use fizix_core::Precision;
+use nalgebra::{Point3, UnitVector3};
+
+#[derive(Copy, Clone, Debug)]
+pub struct Contact {
+    pub point: Point3<Precision>, // on the surface of the shape, world space
+    pub normal: UnitVector3<Precision>, // points away from the shape
+
+    pub depth: Precision
+}
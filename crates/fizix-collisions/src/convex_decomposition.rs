@@ -0,0 +1,391 @@
+use std::collections::{HashMap, HashSet};
+
+use fizix_core::{Precision, EPSILON};
+use nalgebra::{Isometry3, Point3, Vector3};
+use crate::{Compound, ConvexHull, Shape, TriMesh, VoxelGrid};
+
+#[derive(Copy, Clone, Debug)]
+pub struct ConvexDecompositionOptions {
+    pub resolution: usize, // voxels along the longest side of the mesh
+
+    pub max_hulls: usize,
+    pub concavity_tolerance: Precision, // hull volume not covered by voxels, relative to the whole mesh
+
+    pub max_depth: usize, // recursion limit for plane splits
+    pub plane_samples: usize // candidate split planes per axis
+}
+
+impl Default for ConvexDecompositionOptions {
+    fn default() -> Self {
+        Self {
+            resolution: 32,
+
+            max_hulls: 16,
+            concavity_tolerance: 0.02,
+
+            max_depth: 8,
+            plane_samples: 8
+        }
+    }
+}
+
+struct Part {
+    voxels: Vec<Point3<i32>>,
+    hull: ConvexHull,
+
+    concavity: Precision
+}
+
+// voxelises the mesh, splits the voxels along axis planes until each piece is convex enough, then merges hulls down to the target count
+pub fn decompose_convex(mesh: &TriMesh, options: &ConvexDecompositionOptions) -> Vec<ConvexHull> {
+    let grid = voxelize(mesh, options.resolution.max(1));
+    let mut voxels = grid.solid_voxels().collect::<Vec<_>>();
+
+    // hash map order would otherwise leak into the split choices
+    voxels.sort_by_key(|v| (v.z, v.y, v.x));
+
+    let voxel_size = grid.voxel_size();
+    let total_volume = voxels.len() as Precision * voxel_size.powi(3);
+
+    let Some(root) = make_part(voxels, voxel_size, total_volume) else { return Vec::new(); };
+
+    let mut parts = Vec::new();
+
+    split_part(root, voxel_size, total_volume, options, 0, &mut parts);
+
+    merge_parts(parts, total_volume, options.max_hulls.max(1))
+}
+
+pub fn convex_decomposition(mesh: &TriMesh, options: &ConvexDecompositionOptions) -> Compound {
+    Compound::new(
+        decompose_convex(mesh, options).into_iter()
+            .map(|hull| (Isometry3::identity(), Box::new(hull) as Box<dyn Shape>))
+            .collect()
+    )
+}
+
+fn voxelize(mesh: &TriMesh, resolution: usize) -> VoxelGrid {
+    let aabb = mesh.local_aabb();
+    let extents = aabb.max - aabb.min;
+    let voxel_size = extents.max().max(EPSILON) / resolution as Precision;
+
+    let mut grid = VoxelGrid::new(voxel_size);
+
+    if aabb.is_empty() { return grid; }
+
+    // faces lying exactly on the upper bound would otherwise spill into an extra layer
+    let min = grid.voxel_at(&aabb.min);
+    let max = aabb.max.map(|v| (v / voxel_size).ceil() as i32 - 1).sup(&min);
+
+    // interior by ray parity along x, the ray is nudged off voxel centres so it does not graze shared edges
+    let jitter = voxel_size * 1.37e-3;
+
+    for z in min.z..=max.z {
+        for y in min.y..=max.y {
+            let ray_y = (y as Precision + 0.5) * voxel_size + jitter;
+            let ray_z = (z as Precision + 0.5) * voxel_size + jitter * 0.61;
+
+            let mut hits = mesh.triangles()
+                .filter_map(|[a, b, c]| ray_x_triangle(ray_y, ray_z, &a, &b, &c))
+                .collect::<Vec<_>>();
+
+            hits.sort_by(Precision::total_cmp);
+
+            for span in hits.chunks_exact(2) {
+                let start = (span[0] / voxel_size - 0.5).ceil() as i32;
+                let end = (span[1] / voxel_size - 0.5).floor() as i32;
+
+                for x in start..=end {
+                    grid.set_solid(&Point3::new(x, y, z), true);
+                }
+            }
+        }
+    }
+
+    // surface voxels keep thin features that no voxel centre falls inside
+    for [a, b, c] in mesh.triangles() {
+        let steps = (((b - a).norm().max((c - a).norm()) / (voxel_size * 0.5)).ceil() as usize).max(1);
+
+        for i in 0..=steps {
+            for j in 0..=steps - i {
+                let u = i as Precision / steps as Precision;
+                let v = j as Precision / steps as Precision;
+
+                let voxel = grid.voxel_at(&(a + (b - a) * u + (c - a) * v)).sup(&min).inf(&max);
+
+                grid.set_solid(&voxel, true);
+            }
+        }
+    }
+
+    grid
+}
+
+// x coordinate where a ray travelling along +x at (y, z) crosses the triangle
+fn ray_x_triangle(
+    y: Precision,
+    z: Precision,
+    a: &Point3<Precision>,
+    b: &Point3<Precision>,
+    c: &Point3<Precision>
+) -> Option<Precision> {
+    let edge = |p: &Point3<Precision>, q: &Point3<Precision>| (q.y - p.y) * (z - p.z) - (q.z - p.z) * (y - p.y);
+
+    let w_a = edge(b, c);
+    let w_b = edge(c, a);
+    let w_c = edge(a, b);
+
+    let inside = (w_a >= 0.0 && w_b >= 0.0 && w_c >= 0.0) || (w_a <= 0.0 && w_b <= 0.0 && w_c <= 0.0);
+    let area = w_a + w_b + w_c;
+
+    if !inside || area.abs() < EPSILON { return None; }
+
+    Some((w_a * a.x + w_b * b.x + w_c * c.x) / area)
+}
+
+fn make_part(voxels: Vec<Point3<i32>>, voxel_size: Precision, total_volume: Precision) -> Option<Part> {
+    if voxels.is_empty() { return None; }
+
+    let set = voxels.iter().copied().collect::<HashSet<_>>();
+    let mut corners = HashSet::new();
+
+    // interior voxels cannot contribute hull points
+    for voxel in &voxels {
+        if VoxelGrid::FACE_NORMALS.iter().all(|n| set.contains(&(voxel + n))) { continue; }
+
+        for corner in 0..8 {
+            corners.insert(voxel + Vector3::new(corner & 1, (corner >> 1) & 1, (corner >> 2) & 1));
+        }
+    }
+
+    // only the two ends of each row along x can lie on the hull
+    let mut rows: HashMap<(i32, i32), (i32, i32)> = HashMap::new();
+
+    for corner in corners {
+        let row = rows.entry((corner.y, corner.z)).or_insert((corner.x, corner.x));
+
+        *row = (row.0.min(corner.x), row.1.max(corner.x));
+    }
+
+    let mut corners = rows.into_iter()
+        .flat_map(|((y, z), (min_x, max_x))| [Point3::new(min_x, y, z), Point3::new(max_x, y, z)])
+        .collect::<Vec<_>>();
+
+    corners.sort_by_key(|v| (v.z, v.y, v.x));
+    corners.dedup();
+
+    let points = corners.iter()
+        .map(|corner| corner.map(|v| v as Precision * voxel_size))
+        .collect::<Vec<_>>();
+
+    let hull = ConvexHull::from_points(&points)?;
+    let volume = voxels.len() as Precision * voxel_size.powi(3);
+
+    Some(Part {
+        concavity: (hull.volume() - volume).max(0.0) / total_volume,
+
+        voxels, hull
+    })
+}
+
+fn split_part(
+    part: Part,
+    voxel_size: Precision,
+    total_volume: Precision,
+    options: &ConvexDecompositionOptions,
+    depth: usize,
+    parts: &mut Vec<Part>
+) {
+    if part.concavity <= options.concavity_tolerance || depth >= options.max_depth || part.voxels.len() < 2 {
+        parts.push(part);
+
+        return;
+    }
+
+    let min = part.voxels.iter().fold(part.voxels[0], |m, v| m.inf(v));
+    let max = part.voxels.iter().fold(part.voxels[0], |m, v| m.sup(v));
+
+    let mut best: Option<(Precision, Part, Part)> = None;
+
+    for axis in 0..3 {
+        let span = max[axis] - min[axis];
+
+        if span == 0 { continue; }
+
+        let samples = options.plane_samples.clamp(1, span as usize);
+
+        for sample in 1..=samples {
+            let plane = min[axis] + (sample * span as usize / (samples + 1)) as i32;
+
+            let (below, above) = part.voxels.iter().partition::<Vec<_>, _>(|v| v[axis] <= plane);
+
+            let (Some(below), Some(above)) = (
+                make_part(below, voxel_size, total_volume),
+                make_part(above, voxel_size, total_volume)
+            ) else { continue; };
+
+            let cost = below.concavity + above.concavity;
+
+            if best.as_ref().is_none_or(|(best_cost, ..)| cost < *best_cost) {
+                best = Some((cost, below, above));
+            }
+        }
+    }
+
+    match best {
+        Some((_, below, above)) => {
+            split_part(below, voxel_size, total_volume, options, depth + 1, parts);
+            split_part(above, voxel_size, total_volume, options, depth + 1, parts);
+        },
+        None => parts.push(part)
+    }
+}
+
+// greedily merges the pair whose combined hull adds the least volume
+fn merge_parts(parts: Vec<Part>, total_volume: Precision, max_hulls: usize) -> Vec<ConvexHull> {
+    let mut hulls = parts.into_iter().map(|part| part.hull).collect::<Vec<_>>();
+
+    let merge = |a: &ConvexHull, b: &ConvexHull| {
+        let points = a.points().iter().chain(b.points()).copied().collect::<Vec<_>>();
+        let merged = ConvexHull::from_points(&points)?;
+        let cost = (merged.volume() - a.volume() - b.volume()) / total_volume;
+
+        Some((cost, merged))
+    };
+
+    let mut costs = vec![vec![None; hulls.len()]; hulls.len()];
+
+    for i in 0..hulls.len() {
+        for j in i + 1..hulls.len() {
+            costs[i][j] = merge(&hulls[i], &hulls[j]).map(|(cost, _)| cost);
+        }
+    }
+
+    while hulls.len() > max_hulls {
+        let mut best: Option<(Precision, usize, usize)> = None;
+
+        for (i, row) in costs.iter().enumerate() {
+            for (j, cost) in row.iter().enumerate().skip(i + 1) {
+                let Some(cost) = *cost else { continue; };
+
+                if best.is_none_or(|(best_cost, ..)| cost < best_cost) {
+                    best = Some((cost, i, j));
+                }
+            }
+        }
+
+        let Some((_, i, j)) = best else { break; };
+        let Some((_, merged)) = merge(&hulls[i], &hulls[j]) else { break; };
+
+        // j > i, so removing j first keeps i valid
+        hulls.remove(j);
+        costs.remove(j);
+
+        for row in &mut costs {
+            row.remove(j);
+        }
+
+        hulls[i] = merged;
+
+        for k in 0..hulls.len() {
+            if k == i { continue; }
+
+            let cost = merge(&hulls[i], &hulls[k]).map(|(cost, _)| cost);
+
+            if k < i { costs[k][i] = cost; } else { costs[i][k] = cost; }
+        }
+    }
+
+    hulls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the polygon is extruded from z = 0 to z = depth, counter-clockwise so the faces point outwards
+    fn prism(polygon: &[(Precision, Precision)], fan_from: usize, depth: Precision) -> TriMesh {
+        let n = polygon.len();
+        let vertices = [0.0, depth].iter()
+            .flat_map(|&z| polygon.iter().map(move |&(x, y)| Point3::new(x, y, z)))
+            .collect();
+
+        let mut indices = Vec::new();
+
+        for k in 1..n - 1 {
+            let (a, b, c) = (fan_from, (fan_from + k) % n, (fan_from + k + 1) % n);
+
+            indices.push([a as u32, c as u32, b as u32]);
+            indices.push([(a + n) as u32, (b + n) as u32, (c + n) as u32]);
+        }
+
+        for i in 0..n {
+            let j = (i + 1) % n;
+
+            indices.push([i as u32, j as u32, (j + n) as u32]);
+            indices.push([i as u32, (j + n) as u32, (i + n) as u32]);
+        }
+
+        TriMesh::new(vertices, indices)
+    }
+
+    fn l_shape() -> TriMesh {
+        prism(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)], 3, 1.0)
+    }
+
+    fn cuboid() -> TriMesh {
+        prism(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)], 0, 1.0)
+    }
+
+    fn options(max_hulls: usize) -> ConvexDecompositionOptions {
+        ConvexDecompositionOptions { resolution: 16, max_hulls, ..Default::default() }
+    }
+
+    #[test]
+    fn concave_meshes_split_within_the_hull_budget() {
+        for max_hulls in [2, 3, 16] {
+            let hulls = decompose_convex(&l_shape(), &options(max_hulls));
+
+            assert!((2..=max_hulls).contains(&hulls.len()), "{} hulls for a budget of {max_hulls}", hulls.len());
+        }
+    }
+
+    #[test]
+    fn a_single_hull_budget_gives_one_hull() {
+        assert_eq!(decompose_convex(&l_shape(), &options(1)).len(), 1);
+    }
+
+    #[test]
+    fn convex_meshes_stay_whole() {
+        let hulls = decompose_convex(&cuboid(), &options(16));
+
+        assert_eq!(hulls.len(), 1);
+        assert!((hulls[0].volume() - 2.0).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn hulls_stay_within_the_mesh_bounds() {
+        let mesh = l_shape();
+        let bounds = mesh.local_aabb().loosened(1.0e-6);
+
+        for hull in decompose_convex(&mesh, &options(16)) {
+            assert!(hull.points().iter().all(|point| bounds.contains_point(point)));
+        }
+    }
+
+    #[test]
+    fn split_hulls_leave_out_the_concave_corner() {
+        let hulls = decompose_convex(&l_shape(), &options(16));
+        let volume = hulls.iter().map(ConvexHull::volume).sum::<Precision>();
+
+        // the hull of the whole L fills in a corner of 0.5, the voxels round the mesh out by less than that
+        assert!(volume < 3.25, "{volume}");
+    }
+
+    #[test]
+    fn concavity_below_the_tolerance_is_not_split() {
+        let options = ConvexDecompositionOptions { concavity_tolerance: 0.5, ..options(16) };
+
+        assert_eq!(decompose_convex(&l_shape(), &options).len(), 1);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+
+use fizix_core::{Precision, EPSILON};
+use nalgebra::{Point3, Vector3};
+use crate::{closest_point_on_triangle, Aabb, PointProjection, Shape};
+
+#[derive(Clone, Debug)]
+pub struct ConvexHull {
+    points: Vec<Point3<Precision>>,
+    faces: Vec<[usize; 3]>, // counter-clockwise when viewed from outside
+
+    aabb: Aabb
+}
+
+impl ConvexHull {
+    // incremental hull, returns None when the points are coplanar or fewer than four
+    pub fn from_points(points: &[Point3<Precision>]) -> Option<Self> {
+        let aabb = Aabb::from_points(points);
+
+        if aabb.is_empty() { return None; }
+
+        let tolerance = EPSILON * (1.0 + aabb.half_extents().norm());
+        let mut faces = initial_tetrahedron(points, tolerance)?;
+        let mut used = faces.iter().flatten().copied().collect::<HashSet<_>>();
+
+        for (p, point) in points.iter().enumerate() {
+            if used.contains(&p) { continue; }
+
+            let visible = faces.iter()
+                .map(|face| face_distance(points, face, point) > tolerance)
+                .collect::<Vec<_>>();
+
+            if !visible.contains(&true) { continue; }
+
+            let visible_edges = faces.iter()
+                .zip(&visible)
+                .filter(|(_, visible)| **visible)
+                .flat_map(|(&[a, b, c], _)| [(a, b), (b, c), (c, a)])
+                .collect::<Vec<_>>();
+            let edge_set = visible_edges.iter().copied().collect::<HashSet<_>>();
+
+            // horizon edges belong to exactly one visible face
+            let horizon = visible_edges.into_iter()
+                .filter(|(a, b)| !edge_set.contains(&(*b, *a)))
+                .collect::<Vec<_>>();
+
+            let mut visible = visible.into_iter();
+
+            faces.retain(|_| !visible.next().unwrap());
+            faces.extend(horizon.into_iter().map(|(a, b)| [a, b, p]));
+
+            used.insert(p);
+        }
+
+        // compact to the points that ended up on the hull
+        let mut remap = vec![usize::MAX; points.len()];
+        let mut hull_points = Vec::new();
+
+        for face in &mut faces {
+            for i in face.iter_mut() {
+                if remap[*i] == usize::MAX {
+                    remap[*i] = hull_points.len();
+                    hull_points.push(points[*i]);
+                }
+
+                *i = remap[*i];
+            }
+        }
+
+        Some(Self {
+            aabb: Aabb::from_points(&hull_points),
+
+            points: hull_points,
+            faces
+        })
+    }
+
+    #[inline]
+    pub fn points(&self) -> &[Point3<Precision>] {
+        &self.points
+    }
+
+    #[inline]
+    pub fn faces(&self) -> &[[usize; 3]] {
+        &self.faces
+    }
+
+    pub fn volume(&self) -> Precision {
+        self.faces.iter()
+            .map(|&[a, b, c]| self.points[a].coords.dot(&self.points[b].coords.cross(&self.points[c].coords)))
+            .sum::<Precision>() / 6.0
+    }
+
+    pub fn contains_point(&self, point: &Point3<Precision>) -> bool {
+        self.faces.iter().all(|face| face_distance(&self.points, face, point) <= EPSILON)
+    }
+}
+
+impl Shape for ConvexHull {
+    fn local_aabb(&self) -> Aabb {
+        self.aabb
+    }
+
+    fn project_local_point(&self, point: &Point3<Precision>) -> PointProjection {
+        let closest = self.faces.iter()
+            .map(|&[a, b, c]| closest_point_on_triangle(point, &self.points[a], &self.points[b], &self.points[c]))
+            .min_by(|p, q| (p - point).norm_squared().total_cmp(&(q - point).norm_squared()))
+            .unwrap_or(*point);
+
+        PointProjection::new(closest, self.contains_point(point))
+    }
+}
+
+#[inline]
+fn face_normal(points: &[Point3<Precision>], &[a, b, c]: &[usize; 3]) -> Vector3<Precision> {
+    (points[b] - points[a]).cross(&(points[c] - points[a]))
+}
+
+// signed distance of a point above the face plane
+fn face_distance(points: &[Point3<Precision>], face: &[usize; 3], point: &Point3<Precision>) -> Precision {
+    let normal = face_normal(points, face);
+    let length = normal.norm();
+
+    if length < EPSILON { return 0.0; }
+
+    normal.dot(&(point - points[face[0]])) / length
+}
+
+fn initial_tetrahedron(points: &[Point3<Precision>], tolerance: Precision) -> Option<Vec<[usize; 3]>> {
+    let farthest = |score: &dyn Fn(&Point3<Precision>) -> Precision| {
+        (0..points.len()).max_by(|&i, &j| score(&points[i]).total_cmp(&score(&points[j])))
+    };
+
+    let a = farthest(&|p| -p.x)?;
+    let b = farthest(&|p| (p - points[a]).norm_squared())?;
+
+    let line = points[b] - points[a];
+    let c = farthest(&|p| line.cross(&(p - points[a])).norm_squared())?;
+
+    let normal = line.cross(&(points[c] - points[a]));
+
+    if normal.norm() < tolerance { return None; }
+
+    let d = farthest(&|p| normal.dot(&(p - points[a])).abs())?;
+    let height = normal.dot(&(points[d] - points[a]));
+
+    if height.abs() < tolerance * normal.norm() { return None; }
+
+    // orient so that every face points away from d
+    Some(if height > 0.0 {
+        vec![[a, c, b], [a, b, d], [b, c, d], [c, a, d]]
+    } else {
+        vec![[a, b, c], [a, d, b], [b, d, c], [c, d, a]]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube_corners() -> Vec<Point3<Precision>> {
+        (0..8).map(|i| Point3::new((i & 1) as Precision, ((i >> 1) & 1) as Precision, ((i >> 2) & 1) as Precision)).collect()
+    }
+
+    #[test]
+    fn cube_hulls_keep_only_the_corners() {
+        let mut points = cube_corners();
+
+        points.extend([Point3::new(0.5, 0.5, 0.5), Point3::new(0.25, 0.75, 0.1), Point3::new(0.5, 0.5, 1.0)]);
+
+        let hull = ConvexHull::from_points(&points).unwrap();
+
+        assert_eq!(hull.points().len(), 8);
+        assert!(hull.points().iter().all(|point| cube_corners().contains(point)));
+        assert!((hull.volume() - 1.0).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn faces_point_outwards() {
+        let hull = ConvexHull::from_points(&cube_corners()).unwrap();
+        let center = Point3::new(0.5, 0.5, 0.5);
+
+        assert!(hull.faces().iter().all(|face| face_distance(hull.points(), face, &center) < 0.0));
+        assert!(hull.contains_point(&center));
+        assert!(!hull.contains_point(&Point3::new(1.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn flat_point_sets_have_no_hull() {
+        let square = [Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0), Point3::new(1.0, 1.0, 0.0)];
+
+        assert!(ConvexHull::from_points(&square).is_none());
+        assert!(ConvexHull::from_points(&square[..3]).is_none());
+    }
+}
@@ -0,0 +1,27 @@
+mod aabb;
+mod broad_phase;
+mod collider;
+mod compound;
+mod contact;
+mod convex_decomposition;
+mod convex_hull;
+mod mesh_import;
+mod projection;
+mod shape;
+mod spatial_hash;
+mod tri_mesh;
+mod voxel_grid;
+
+pub use aabb::*;
+pub use broad_phase::*;
+pub use collider::*;
+pub use compound::*;
+pub use contact::*;
+pub use convex_decomposition::*;
+pub use convex_hull::*;
+pub use mesh_import::*;
+pub use projection::*;
+pub use shape::*;
+pub use spatial_hash::*;
+pub use tri_mesh::*;
+pub use voxel_grid::*;
@@ -0,0 +1,381 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use fizix_core::{Precision, EPSILON};
+use nalgebra::{Point3, Vector3};
+use crate::{ConvexHull, TriMesh};
+
+type RawMesh = (Vec<Point3<Precision>>, Vec<[u32; 3]>);
+
+#[derive(Debug)]
+pub enum MeshImportError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    Empty
+}
+
+impl fmt::Display for MeshImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshImportError::Io(err) => write!(f, "io error: {err}"),
+            MeshImportError::Parse { line, message } => write!(f, "parse error on line {line}: {message}"),
+            MeshImportError::Empty => write!(f, "mesh has no usable triangles")
+        }
+    }
+}
+
+impl std::error::Error for MeshImportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MeshImportError::Io(err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for MeshImportError {
+    fn from(err: io::Error) -> Self {
+        MeshImportError::Io(err)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct MeshImportOptions {
+    pub scale: Vector3<Precision>,
+
+    pub weld_distance: Precision, // vertices closer than this are merged, 0 disables welding
+    pub remove_degenerate: bool // drops triangles with repeated vertices or no area
+}
+
+impl Default for MeshImportOptions {
+    fn default() -> Self {
+        Self {
+            scale: Vector3::repeat(1.0),
+
+            weld_distance: 1e-6,
+            remove_degenerate: true
+        }
+    }
+}
+
+pub fn load_obj(reader: impl BufRead, options: &MeshImportOptions) -> Result<TriMesh, MeshImportError> {
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let number = number + 1;
+        let parse_error = |message: &str| MeshImportError::Parse { line: number, message: message.to_owned() };
+
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => {
+                let mut coords = [0.0; 3];
+
+                for coord in &mut coords {
+                    *coord = tokens.next()
+                        .and_then(|t| t.parse::<Precision>().ok())
+                        .ok_or_else(|| parse_error("expected three vertex coordinates"))?;
+                }
+
+                vertices.push(Point3::from(coords));
+            },
+            Some("f") => {
+                let mut face = Vec::new();
+
+                for token in tokens {
+                    // only the position index of v, v/vt, v/vt/vn or v//vn is used
+                    let index = token.split('/').next()
+                        .and_then(|t| t.parse::<i64>().ok())
+                        .ok_or_else(|| parse_error("invalid face index"))?;
+
+                    // negative indices count back from the latest vertex
+                    let index = if index < 0 { vertices.len() as i64 + index } else { index - 1 };
+
+                    if index < 0 || index >= vertices.len() as i64 {
+                        return Err(parse_error("face index out of range"));
+                    }
+
+                    face.push(index as u32);
+                }
+
+                if face.len() < 3 { return Err(parse_error("face needs at least three vertices")); }
+
+                // fan triangulation, assumes convex polygons
+                for i in 1..face.len() - 1 {
+                    triangles.push([face[0], face[i], face[i + 1]]);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    build_mesh(vertices, triangles, options)
+}
+
+pub fn load_obj_file(path: impl AsRef<Path>, options: &MeshImportOptions) -> Result<TriMesh, MeshImportError> {
+    load_obj(BufReader::new(File::open(path)?), options)
+}
+
+// binary files may also start with "solid", so ascii is only assumed when the whole file is text
+pub fn load_stl(mut reader: impl Read, options: &MeshImportOptions) -> Result<TriMesh, MeshImportError> {
+    let mut bytes = Vec::new();
+
+    reader.read_to_end(&mut bytes)?;
+
+    let (vertices, triangles) = if is_ascii_stl(&bytes) {
+        parse_ascii_stl(&bytes)?
+    } else {
+        parse_binary_stl(&bytes)?
+    };
+
+    build_mesh(vertices, triangles, options)
+}
+
+pub fn load_stl_file(path: impl AsRef<Path>, options: &MeshImportOptions) -> Result<TriMesh, MeshImportError> {
+    load_stl(BufReader::new(File::open(path)?), options)
+}
+
+pub fn load_obj_convex_hull(reader: impl BufRead, options: &MeshImportOptions) -> Result<ConvexHull, MeshImportError> {
+    load_obj(reader, options)?.convex_hull().ok_or(MeshImportError::Empty)
+}
+
+pub fn load_stl_convex_hull(reader: impl Read, options: &MeshImportOptions) -> Result<ConvexHull, MeshImportError> {
+    load_stl(reader, options)?.convex_hull().ok_or(MeshImportError::Empty)
+}
+
+fn is_ascii_stl(bytes: &[u8]) -> bool {
+    bytes.trim_ascii_start().starts_with(b"solid") && bytes.is_ascii()
+}
+
+fn parse_binary_stl(bytes: &[u8]) -> Result<RawMesh, MeshImportError> {
+    let truncated = || MeshImportError::Parse { line: 0, message: "binary stl is shorter than its triangle count".to_owned() };
+
+    let header = bytes.get(80..84).ok_or_else(truncated)?;
+    let count = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+
+    // some exporters pad the file, anything past the last record is ignored
+    let records = bytes.get(84..84 + count * 50).ok_or_else(truncated)?;
+
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+
+    // each record is a normal, three vertices and a two byte attribute
+    for record in records.chunks_exact(50) {
+        let read = |offset: usize| f32::from_le_bytes([
+            record[offset], record[offset + 1], record[offset + 2], record[offset + 3]
+        ]) as Precision;
+
+        let first = vertices.len() as u32;
+
+        for v in 0..3 {
+            let offset = 12 + v * 12;
+
+            vertices.push(Point3::new(read(offset), read(offset + 4), read(offset + 8)));
+        }
+
+        triangles.push([first, first + 1, first + 2]);
+    }
+
+    Ok((vertices, triangles))
+}
+
+fn parse_ascii_stl(bytes: &[u8]) -> Result<RawMesh, MeshImportError> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| MeshImportError::Parse { line: 0, message: "ascii stl is not valid text".to_owned() })?;
+
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    let mut facet = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let parse_error = |message: &str| MeshImportError::Parse { line: number + 1, message: message.to_owned() };
+
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("vertex") => {
+                let mut coords = [0.0; 3];
+
+                for coord in &mut coords {
+                    *coord = tokens.next()
+                        .and_then(|t| t.parse::<Precision>().ok())
+                        .ok_or_else(|| parse_error("expected three vertex coordinates"))?;
+                }
+
+                facet.push(vertices.len() as u32);
+                vertices.push(Point3::from(coords));
+            },
+            Some("endfacet") => {
+                if facet.len() != 3 { return Err(parse_error("facet must have exactly three vertices")); }
+
+                triangles.push([facet[0], facet[1], facet[2]]);
+                facet.clear();
+            },
+            _ => {}
+        }
+    }
+
+    Ok((vertices, triangles))
+}
+
+fn build_mesh(
+    mut vertices: Vec<Point3<Precision>>,
+    mut triangles: Vec<[u32; 3]>,
+    options: &MeshImportOptions
+) -> Result<TriMesh, MeshImportError> {
+    for vertex in &mut vertices {
+        vertex.coords.component_mul_assign(&options.scale);
+    }
+
+    // an odd number of negative scale factors mirrors the mesh, which would turn it inside out
+    if options.scale.x * options.scale.y * options.scale.z < 0.0 {
+        for triangle in &mut triangles {
+            triangle.swap(1, 2);
+        }
+    }
+
+    if options.weld_distance > 0.0 {
+        let remap = weld_vertices(&mut vertices, options.weld_distance);
+
+        for triangle in &mut triangles {
+            for i in triangle.iter_mut() {
+                *i = remap[*i as usize];
+            }
+        }
+    }
+
+    if options.remove_degenerate {
+        triangles.retain(|&[a, b, c]| {
+            if a == b || b == c || c == a { return false; }
+
+            let (a, b, c) = (vertices[a as usize], vertices[b as usize], vertices[c as usize]);
+
+            (b - a).cross(&(c - a)).norm_squared() > EPSILON * EPSILON
+        });
+    }
+
+    if triangles.is_empty() { return Err(MeshImportError::Empty); }
+
+    Ok(TriMesh::new(vertices, triangles))
+}
+
+// merges vertices within the weld distance using a hash grid, returns the old to new index map
+fn weld_vertices(vertices: &mut Vec<Point3<Precision>>, distance: Precision) -> Vec<u32> {
+    let distance_sq = distance * distance;
+    let cell_of = |p: &Point3<Precision>| p.map(|v| (v / distance).floor() as i64);
+
+    let mut cells: HashMap<Point3<i64>, Vec<u32>> = HashMap::new();
+    let mut welded: Vec<Point3<Precision>> = Vec::new();
+    let mut remap = Vec::with_capacity(vertices.len());
+
+    for vertex in vertices.iter() {
+        let cell = cell_of(vertex);
+        let mut found = None;
+
+        'search: for z in -1..=1 {
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    let Some(candidates) = cells.get(&(cell + Vector3::new(x, y, z))) else { continue; };
+
+                    if let Some(&i) = candidates.iter().find(|&&i| (welded[i as usize] - vertex).norm_squared() <= distance_sq) {
+                        found = Some(i);
+
+                        break 'search;
+                    }
+                }
+            }
+        }
+
+        let index = found.unwrap_or_else(|| {
+            welded.push(*vertex);
+            cells.entry(cell).or_default().push(welded.len() as u32 - 1);
+
+            welded.len() as u32 - 1
+        });
+
+        remap.push(index);
+    }
+
+    *vertices = welded;
+
+    remap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TETRAHEDRON_OBJ: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 3 2\nf 1 2 4\nf 1 4 3\nf 2 3 4\n";
+
+    // positive while the triangles wind counter clockwise seen from outside
+    fn signed_volume(mesh: &TriMesh) -> Precision {
+        mesh.triangles().map(|[a, b, c]| a.coords.dot(&b.coords.cross(&c.coords)) / 6.0).sum()
+    }
+
+    fn binary_stl(header: &[u8], triangles: &[[[f32; 3]; 3]], padding: usize) -> Vec<u8> {
+        let mut bytes = header.to_vec();
+
+        bytes.resize(80, b' ');
+        bytes.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+
+        for triangle in triangles {
+            bytes.extend_from_slice(&[0; 12]);
+
+            for coord in triangle.iter().flatten() {
+                bytes.extend_from_slice(&coord.to_le_bytes());
+            }
+
+            bytes.extend_from_slice(&[0; 2]);
+        }
+
+        bytes.resize(bytes.len() + padding, 0);
+        bytes
+    }
+
+    #[test]
+    fn mirroring_scale_keeps_outward_winding() {
+        let options = MeshImportOptions::default();
+        let mirrored = MeshImportOptions { scale: Vector3::new(-1.0, 1.0, 1.0), ..options };
+
+        let mesh = load_obj(TETRAHEDRON_OBJ.as_bytes(), &options).unwrap();
+        let mirrored_mesh = load_obj(TETRAHEDRON_OBJ.as_bytes(), &mirrored).unwrap();
+
+        assert!(signed_volume(&mesh) > 0.0);
+        assert!((signed_volume(&mirrored_mesh) - signed_volume(&mesh)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn binary_stl_with_solid_header_and_padding() {
+        let triangle = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let bytes = binary_stl(b"solid exported by some tool", &[triangle], 7);
+
+        let mesh = load_stl(bytes.as_slice(), &MeshImportOptions::default()).unwrap();
+
+        assert_eq!(mesh.indices().len(), 1);
+        assert_eq!(mesh.vertices()[1], Point3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn truncated_binary_stl_is_an_error() {
+        let triangle = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+        let mut bytes = binary_stl(b"binary", &[triangle, triangle], 0);
+
+        bytes.truncate(bytes.len() - 10);
+
+        assert!(matches!(load_stl(bytes.as_slice(), &MeshImportOptions::default()), Err(MeshImportError::Parse { .. })));
+    }
+
+    #[test]
+    fn ascii_stl() {
+        let text = "solid test\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nvertex 0 1 0\nendloop\nendfacet\nendsolid test\n";
+
+        let mesh = load_stl(text.as_bytes(), &MeshImportOptions::default()).unwrap();
+
+        assert_eq!(mesh.indices().len(), 1);
+    }
+}
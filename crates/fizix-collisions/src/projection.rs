@@ -0,0 +1,143 @@
+use fizix_core::{math, Precision, World};
+use nalgebra::{Isometry3, Point3};
+use crate::{ColliderHandle, ColliderSet, Shape};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PointProjection {
+    pub point: Point3<Precision>, // nearest point on the surface
+    pub is_inside: bool
+}
+
+impl PointProjection {
+    #[inline]
+    pub fn new(point: Point3<Precision>, is_inside: bool) -> Self {
+        Self { point, is_inside }
+    }
+
+    #[inline]
+    pub fn transform_by(&self, pose: &Isometry3<Precision>) -> Self {
+        Self::new(pose * self.point, self.is_inside)
+    }
+}
+
+pub fn project_point(shape: &dyn Shape, pose: &Isometry3<Precision>, point: &Point3<Precision>) -> PointProjection {
+    shape.project_local_point(&pose.inverse_transform_point(point)).transform_by(pose)
+}
+
+// queries against every collider attached to the world's bodies
+pub trait WorldPointQuery {
+    fn project_point(&self, colliders: &ColliderSet, point: &Point3<Precision>) -> Option<(ColliderHandle, PointProjection)>;
+
+    fn contains_point(&self, colliders: &ColliderSet, point: &Point3<Precision>) -> bool;
+}
+
+impl WorldPointQuery for World {
+    #[inline]
+    fn project_point(&self, colliders: &ColliderSet, point: &Point3<Precision>) -> Option<(ColliderHandle, PointProjection)> {
+        colliders.project_point(self.bodies(), point)
+    }
+
+    #[inline]
+    fn contains_point(&self, colliders: &ColliderSet, point: &Point3<Precision>) -> bool {
+        colliders.contains_point(self.bodies(), point)
+    }
+}
+
+// Ericson, Real-Time Collision Detection 5.1.5
+pub(crate) fn closest_point_on_triangle(
+    point: &Point3<Precision>,
+    a: &Point3<Precision>,
+    b: &Point3<Precision>,
+    c: &Point3<Precision>
+) -> Point3<Precision> {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = point - a;
+
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+
+    if d1 <= 0.0 && d2 <= 0.0 { return *a; }
+
+    let bp = point - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+
+    if d3 >= 0.0 && d4 <= d3 { return *b; }
+
+    let vc = d1 * d4 - d3 * d2;
+
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = point - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+
+    if d6 >= 0.0 && d5 <= d6 { return *c; }
+
+    let vb = d5 * d2 - d1 * d6;
+
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denominator = 1.0 / (va + vb + vc);
+
+    a + ab * (vb * denominator) + ac * (vc * denominator)
+}
+
+// solid angle subtended by the triangle, Van Oosterom and Strackee
+pub(crate) fn triangle_solid_angle(
+    point: &Point3<Precision>,
+    a: &Point3<Precision>,
+    b: &Point3<Precision>,
+    c: &Point3<Precision>
+) -> Precision {
+    let a = a - point;
+    let b = b - point;
+    let c = c - point;
+
+    let (la, lb, lc) = (a.norm(), b.norm(), c.norm());
+
+    let numerator = a.dot(&b.cross(&c));
+    let denominator = la * lb * lc + a.dot(&b) * lc + b.dot(&c) * la + c.dot(&a) * lb;
+
+    2.0 * math::atan2(numerator, denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use fizix_core::RigidBodyDesc;
+    use nalgebra::Vector3;
+    use crate::{Ball, Collider, Cuboid};
+    use super::*;
+
+    #[test]
+    fn world_projection_follows_the_bodies() {
+        let mut world = World::new(Vector3::zeros(), 1, 1);
+        let mut colliders = ColliderSet::new();
+
+        let body = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(5.0, 0.0, 0.0)));
+        let ball = colliders.add(Collider::new(Ball::new(1.0)).attached_to(body));
+
+        colliders.add(Collider::new(Cuboid::new(Vector3::repeat(0.5))).with_local_pose(Isometry3::translation(0.0, -10.0, 0.0)));
+
+        let (handle, projection) = world.project_point(&colliders, &Point3::origin()).unwrap();
+
+        assert_eq!(handle, ball);
+        assert!(!projection.is_inside);
+        assert!((projection.point - Point3::new(4.0, 0.0, 0.0)).norm() < 1e-6);
+
+        assert!(world.contains_point(&colliders, &Point3::new(5.5, 0.0, 0.0)));
+        assert!(world.contains_point(&colliders, &Point3::new(0.0, -10.0, 0.0)));
+        assert!(!world.contains_point(&colliders, &Point3::new(0.0, 5.0, 0.0)));
+    }
+}
@@ -0,0 +1,73 @@
+use fizix_core::{Precision, EPSILON};
+use nalgebra::{Isometry3, Point3, Vector3};
+use crate::{Aabb, PointProjection};
+
+pub trait Shape {
+    fn local_aabb(&self) -> Aabb;
+
+    fn project_local_point(&self, point: &Point3<Precision>) -> PointProjection;
+
+    fn compute_aabb(&self, pose: &Isometry3<Precision>) -> Aabb {
+        self.local_aabb().transform_by(pose)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Ball {
+    pub radius: Precision
+}
+
+impl Ball {
+    pub fn new(radius: Precision) -> Self {
+        Self { radius }
+    }
+}
+
+impl Shape for Ball {
+    fn local_aabb(&self) -> Aabb {
+        Aabb::new(Point3::origin(), Point3::origin()).loosened(self.radius)
+    }
+
+    fn project_local_point(&self, point: &Point3<Precision>) -> PointProjection {
+        let distance = point.coords.norm();
+
+        // any surface point is nearest from the centre
+        let direction = if distance > EPSILON { point.coords / distance } else { Vector3::x() };
+
+        PointProjection::new(Point3::from(direction * self.radius), distance <= self.radius)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Cuboid {
+    pub half_extents: Vector3<Precision>
+}
+
+impl Cuboid {
+    pub fn new(half_extents: Vector3<Precision>) -> Self {
+        Self { half_extents }
+    }
+}
+
+impl Shape for Cuboid {
+    fn local_aabb(&self) -> Aabb {
+        Aabb::new(Point3::from(-self.half_extents), Point3::from(self.half_extents))
+    }
+
+    fn project_local_point(&self, point: &Point3<Precision>) -> PointProjection {
+        let clamped = point.coords.zip_map(&self.half_extents, |p, h| p.clamp(-h, h));
+
+        if clamped != point.coords {
+            return PointProjection::new(Point3::from(clamped), false);
+        }
+
+        // inside, push out through the nearest face
+        let gaps = self.half_extents - point.coords.abs();
+        let axis = gaps.imin();
+        let mut projected = *point;
+
+        projected[axis] = self.half_extents[axis].copysign(point[axis]);
+
+        PointProjection::new(projected, true)
+    }
+}
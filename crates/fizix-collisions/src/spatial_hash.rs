@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use fizix_core::Precision;
+use nalgebra::{Point3, Vector3};
+
+// uniform grid broadphase for many particles of similar size, indices refer to the caller's position slice
+pub struct SpatialHash {
+    cell_size: Precision,
+
+    cells: HashMap<Point3<i32>, Vec<usize>>,
+    particle_cells: Vec<Point3<i32>>
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: Precision) -> Self {
+        assert_valid_cell_size(cell_size);
+
+        Self {
+            cell_size,
+
+            cells: HashMap::new(),
+            particle_cells: Vec::new()
+        }
+    }
+
+    #[inline]
+    pub fn cell_size(&self) -> Precision {
+        self.cell_size
+    }
+
+    // existing cells no longer line up, call rebuild afterwards
+    pub fn set_cell_size(&mut self, cell_size: Precision) {
+        assert_valid_cell_size(cell_size);
+
+        self.cell_size = cell_size;
+
+        self.clear();
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.particle_cells.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.particle_cells.is_empty()
+    }
+
+    #[inline]
+    pub fn cell_of(&self, point: &Point3<Precision>) -> Point3<i32> {
+        point.map(|v| (v / self.cell_size).floor() as i32)
+    }
+
+    pub fn clear(&mut self) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+
+        self.particle_cells.clear();
+    }
+
+    pub fn rebuild(&mut self, positions: &[Point3<Precision>]) {
+        self.clear();
+
+        for (i, position) in positions.iter().enumerate() {
+            let cell = self.cell_of(position);
+
+            self.cells.entry(cell).or_default().push(i);
+            self.particle_cells.push(cell);
+        }
+
+        // drop cells that stayed empty so the map does not grow without bound
+        self.cells.retain(|_, cell| !cell.is_empty());
+    }
+
+    // moves only the particles that changed cell, cheaper than a rebuild when most are at rest
+    pub fn update(&mut self, positions: &[Point3<Precision>]) {
+        if positions.len() != self.particle_cells.len() {
+            self.rebuild(positions);
+
+            return;
+        }
+
+        for (i, position) in positions.iter().enumerate() {
+            let cell = self.cell_of(position);
+            let old_cell = self.particle_cells[i];
+
+            if cell == old_cell { continue; }
+
+            if let Some(particles) = self.cells.get_mut(&old_cell) {
+                if let Some(slot) = particles.iter().position(|&p| p == i) {
+                    particles.swap_remove(slot);
+                }
+
+                if particles.is_empty() {
+                    self.cells.remove(&old_cell);
+                }
+            }
+
+            self.cells.entry(cell).or_default().push(i);
+            self.particle_cells[i] = cell;
+        }
+    }
+
+    // cells are visited in grid order, or in map order when the query box spans more cells than are occupied
+    pub fn for_each_in_radius(
+        &self,
+        positions: &[Point3<Precision>],
+        point: &Point3<Precision>,
+        radius: Precision,
+        mut f: impl FnMut(usize)
+    ) {
+        let radius_sq = radius * radius;
+        let min = self.cell_of(&(point - Vector3::repeat(radius)));
+        let max = self.cell_of(&(point + Vector3::repeat(radius)));
+
+        let mut visit = |particles: &Vec<usize>| {
+            for &i in particles {
+                if (positions[i] - point).norm_squared() <= radius_sq {
+                    f(i);
+                }
+            }
+        };
+
+        let box_cells = (max - min).iter().fold(1u64, |count, &span| count.saturating_mul(span as u64 + 1));
+
+        if box_cells > self.cells.len() as u64 {
+            self.cells.iter()
+                .filter(|(cell, _)| cell.iter().zip(min.iter().zip(max.iter())).all(|(v, (lo, hi))| lo <= v && v <= hi))
+                .for_each(|(_, particles)| visit(particles));
+
+            return;
+        }
+
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let Some(particles) = self.cells.get(&Point3::new(x, y, z)) else { continue; };
+
+                    visit(particles);
+                }
+            }
+        }
+    }
+
+    pub fn query_radius(
+        &self,
+        positions: &[Point3<Precision>],
+        point: &Point3<Precision>,
+        radius: Precision,
+        out: &mut Vec<usize>
+    ) {
+        out.clear();
+
+        self.for_each_in_radius(positions, point, radius, |i| out.push(i));
+
+        // the visiting order depends on the query size, the result should not
+        out.sort_unstable();
+    }
+
+    // pairs (i, j) with i < j closer than radius, in particle order
+    pub fn neighbour_pairs(&self, positions: &[Point3<Precision>], radius: Precision, out: &mut Vec<(usize, usize)>) {
+        out.clear();
+
+        for (i, position) in positions.iter().enumerate() {
+            let first = out.len();
+
+            self.for_each_in_radius(positions, position, radius, |j| {
+                if j > i { out.push((i, j)); }
+            });
+
+            out[first..].sort_unstable();
+        }
+    }
+}
+
+#[inline]
+fn assert_valid_cell_size(cell_size: Precision) {
+    assert!(cell_size.is_finite() && cell_size > 0.0, "cell size has to be finite and positive, got {cell_size}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a loose cloud with a few particles sharing cells
+    fn cloud() -> Vec<Point3<Precision>> {
+        (0..200).map(|i| {
+            let i = i as Precision;
+
+            Point3::new((i * 0.37).sin() * 3.0, (i * 0.91).cos() * 3.0, (i * 0.13).sin() * 3.0)
+        }).collect()
+    }
+
+    fn brute_force(positions: &[Point3<Precision>], point: &Point3<Precision>, radius: Precision) -> Vec<usize> {
+        (0..positions.len()).filter(|&i| (positions[i] - point).norm() <= radius).collect()
+    }
+
+    #[test]
+    fn radius_queries_match_brute_force() {
+        let positions = cloud();
+        let mut hash = SpatialHash::new(0.5);
+        let mut found = Vec::new();
+
+        hash.rebuild(&positions);
+
+        for radius in [0.1, 0.7, 2.0, 50.0] {
+            for point in [Point3::origin(), Point3::new(1.0, -2.0, 0.5), positions[17]] {
+                hash.query_radius(&positions, &point, radius, &mut found);
+
+                assert_eq!(found, brute_force(&positions, &point, radius), "radius {radius} around {point}");
+            }
+        }
+    }
+
+    #[test]
+    fn updates_match_a_rebuild() {
+        let mut positions = cloud();
+        let mut updated = SpatialHash::new(0.5);
+        let mut rebuilt = SpatialHash::new(0.5);
+
+        updated.rebuild(&positions);
+
+        for (i, position) in positions.iter_mut().enumerate().step_by(3) {
+            *position += Vector3::new(0.3, -0.8, i as Precision * 0.01);
+        }
+
+        updated.update(&positions);
+        rebuilt.rebuild(&positions);
+
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+
+        updated.neighbour_pairs(&positions, 0.6, &mut a);
+        rebuilt.neighbour_pairs(&positions, 0.6, &mut b);
+
+        assert_eq!(a, b);
+        assert_eq!(updated.cells.len(), rebuilt.cells.len());
+        assert_eq!(updated.particle_cells, rebuilt.particle_cells);
+    }
+
+    #[test]
+    fn updates_with_a_new_particle_count_rebuild() {
+        let positions = cloud();
+        let mut hash = SpatialHash::new(0.5);
+
+        hash.rebuild(&positions[..10]);
+        hash.update(&positions);
+
+        assert_eq!(hash.len(), positions.len());
+    }
+
+    #[test]
+    fn neighbour_pairs_match_brute_force() {
+        let positions = cloud();
+        let mut hash = SpatialHash::new(0.4);
+        let mut pairs = Vec::new();
+
+        hash.rebuild(&positions);
+        hash.neighbour_pairs(&positions, 0.4, &mut pairs);
+
+        let expected = (0..positions.len())
+            .flat_map(|i| (i + 1..positions.len()).map(move |j| (i, j)))
+            .filter(|&(i, j)| (positions[i] - positions[j]).norm() <= 0.4)
+            .collect::<Vec<_>>();
+
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn large_queries_over_small_cells_only_visit_occupied_cells() {
+        let positions = [Point3::new(0.0, 0.0, 0.0), Point3::new(4.0, 0.0, 0.0), Point3::new(0.0, 6.0, 0.0)];
+        let mut hash = SpatialHash::new(1.0e-3);
+        let mut found = Vec::new();
+
+        hash.rebuild(&positions);
+        hash.query_radius(&positions, &Point3::origin(), 5.0, &mut found);
+
+        assert_eq!(found, [0, 1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_cell_sizes_are_rejected() {
+        SpatialHash::new(0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn nan_cell_sizes_are_rejected() {
+        SpatialHash::new(1.0).set_cell_size(Precision::NAN);
+    }
+}
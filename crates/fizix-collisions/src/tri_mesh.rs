@@ -0,0 +1,73 @@
+
+use fizix_core::consts::PI;
+use fizix_core::Precision;
+use nalgebra::Point3;
+use crate::{closest_point_on_triangle, triangle_solid_angle, Aabb, ConvexHull, PointProjection, Shape};
+
+#[derive(Clone, Debug)]
+pub struct TriMesh {
+    vertices: Vec<Point3<Precision>>,
+    indices: Vec<[u32; 3]>,
+
+    aabb: Aabb
+}
+
+impl TriMesh {
+    pub fn new(vertices: Vec<Point3<Precision>>, indices: Vec<[u32; 3]>) -> Self {
+        Self {
+            aabb: Aabb::from_points(&vertices),
+
+            vertices, indices
+        }
+    }
+
+    #[inline]
+    pub fn vertices(&self) -> &[Point3<Precision>] {
+        &self.vertices
+    }
+
+    #[inline]
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [Point3<Precision>; 3]> + '_ {
+        self.indices.iter().map(|&[a, b, c]| [
+            self.vertices[a as usize],
+            self.vertices[b as usize],
+            self.vertices[c as usize]
+        ])
+    }
+
+    #[inline]
+    pub fn convex_hull(&self) -> Option<ConvexHull> {
+        ConvexHull::from_points(&self.vertices)
+    }
+}
+
+impl Shape for TriMesh {
+    fn local_aabb(&self) -> Aabb {
+        self.aabb
+    }
+
+    // the inside flag uses the winding number, so it is only meaningful for closed meshes
+    fn project_local_point(&self, point: &Point3<Precision>) -> PointProjection {
+        let mut closest = *point;
+        let mut closest_distance_sq = Precision::INFINITY;
+        let mut solid_angle = 0.0;
+
+        for [a, b, c] in self.triangles() {
+            let candidate = closest_point_on_triangle(point, &a, &b, &c);
+            let distance_sq = (candidate - point).norm_squared();
+
+            if distance_sq < closest_distance_sq {
+                closest = candidate;
+                closest_distance_sq = distance_sq;
+            }
+
+            solid_angle += triangle_solid_angle(point, &a, &b, &c);
+        }
+
+        PointProjection::new(closest, solid_angle.abs() > 2.0 * PI)
+    }
+}
@@ -0,0 +1,491 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use fizix_core::Precision;
+use nalgebra::{Isometry3, Point3, UnitVector3, Vector3};
+use crate::{Aabb, BroadPhase, Contact, PointProjection, ProxyId, Shape};
+
+pub const CHUNK_SIZE: i32 = 8;
+
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+#[derive(Clone)]
+struct Chunk {
+    solid: [u64; CHUNK_VOLUME / 64],
+    count: usize
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Self { solid: [0; CHUNK_VOLUME / 64], count: 0 }
+    }
+
+    #[inline]
+    fn index(local: &Point3<i32>) -> usize {
+        (local.x + local.y * CHUNK_SIZE + local.z * CHUNK_SIZE * CHUNK_SIZE) as usize
+    }
+
+    #[inline]
+    fn get(&self, local: &Point3<i32>) -> bool {
+        let i = Self::index(local);
+
+        self.solid[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    // returns whether the voxel changed
+    fn set(&mut self, local: &Point3<i32>, solid: bool) -> bool {
+        if self.get(local) == solid { return false; }
+
+        let i = Self::index(local);
+
+        self.solid[i / 64] ^= 1 << (i % 64);
+
+        if solid { self.count += 1; } else { self.count -= 1; }
+
+        true
+    }
+
+    fn solid_locals(&self) -> impl Iterator<Item = Point3<i32>> + '_ {
+        (0..CHUNK_VOLUME as i32).filter_map(move |i| {
+            let local = Point3::new(i % CHUNK_SIZE, (i / CHUNK_SIZE) % CHUNK_SIZE, i / (CHUNK_SIZE * CHUNK_SIZE));
+
+            self.get(&local).then_some(local)
+        })
+    }
+
+    // voxel bounds of the solid cells, in chunk local coordinates
+    fn occupied_bounds(&self) -> Option<(Point3<i32>, Point3<i32>)> {
+        let mut bounds: Option<(Point3<i32>, Point3<i32>)> = None;
+
+        for z in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    let local = Point3::new(x, y, z);
+
+                    if !self.get(&local) { continue; }
+
+                    bounds = Some(match bounds {
+                        Some((min, max)) => (min.inf(&local), max.sup(&local)),
+                        None => (local, local)
+                    });
+                }
+            }
+        }
+
+        bounds
+    }
+}
+
+// sparse grid of unit cubes, stored in chunks that are created and dropped as voxels are edited
+pub struct VoxelGrid {
+    voxel_size: Precision,
+
+    chunks: HashMap<Point3<i32>, Chunk>,
+
+    // broadphase bookkeeping, one proxy per non-empty chunk
+    proxies: HashMap<Point3<i32>, ProxyId>,
+    dirty_chunks: HashSet<Point3<i32>>
+}
+
+impl VoxelGrid {
+    pub const FACE_NORMALS: [Vector3<i32>; 6] = [
+        Vector3::new(1, 0, 0), Vector3::new(-1, 0, 0),
+        Vector3::new(0, 1, 0), Vector3::new(0, -1, 0),
+        Vector3::new(0, 0, 1), Vector3::new(0, 0, -1)
+    ];
+
+    pub fn new(voxel_size: Precision) -> Self {
+        Self {
+            voxel_size,
+
+            chunks: HashMap::new(),
+
+            proxies: HashMap::new(),
+            dirty_chunks: HashSet::new()
+        }
+    }
+
+    #[inline]
+    pub fn voxel_size(&self) -> Precision {
+        self.voxel_size
+    }
+
+    #[inline]
+    fn split(voxel: &Point3<i32>) -> (Point3<i32>, Point3<i32>) {
+        let chunk = voxel.map(|v| v.div_euclid(CHUNK_SIZE));
+        let local = voxel.map(|v| v.rem_euclid(CHUNK_SIZE));
+
+        (chunk, local)
+    }
+
+    pub fn is_solid(&self, voxel: &Point3<i32>) -> bool {
+        let (chunk, local) = Self::split(voxel);
+
+        self.chunks.get(&chunk).is_some_and(|c| c.get(&local))
+    }
+
+    pub fn set_solid(&mut self, voxel: &Point3<i32>, solid: bool) {
+        let (chunk_key, local) = Self::split(voxel);
+
+        let changed = match self.chunks.get_mut(&chunk_key) {
+            Some(chunk) => {
+                let changed = chunk.set(&local, solid);
+
+                if chunk.count == 0 {
+                    self.chunks.remove(&chunk_key);
+                }
+
+                changed
+            },
+            None if solid => self.chunks.entry(chunk_key).or_insert_with(Chunk::new).set(&local, true),
+            None => false
+        };
+
+        if changed {
+            self.dirty_chunks.insert(chunk_key);
+        }
+    }
+
+    #[inline]
+    pub fn solid_count(&self) -> usize {
+        self.chunks.values().map(|c| c.count).sum()
+    }
+
+    // ordered by chunk so the same grid always lists its voxels the same way
+    pub fn solid_voxels(&self) -> impl Iterator<Item = Point3<i32>> + '_ {
+        let mut keys = self.chunks.keys().copied().collect::<Vec<_>>();
+
+        keys.sort_by(key_order);
+
+        keys.into_iter().flat_map(|key| {
+            let origin = key * CHUNK_SIZE;
+
+            self.chunks[&key].solid_locals().map(move |local| origin + local.coords)
+        })
+    }
+
+    // voxel containing a point in grid local space
+    #[inline]
+    pub fn voxel_at(&self, point: &Point3<Precision>) -> Point3<i32> {
+        point.map(|v| (v / self.voxel_size).floor() as i32)
+    }
+
+    #[inline]
+    pub fn voxel_aabb(&self, voxel: &Point3<i32>) -> Aabb {
+        let min = voxel.map(|v| v as Precision * self.voxel_size);
+
+        Aabb::new(min, min + Vector3::repeat(self.voxel_size))
+    }
+
+    // a face is exposed when the neighbouring voxel across it is empty
+    #[inline]
+    pub fn is_face_exposed(&self, voxel: &Point3<i32>, face: usize) -> bool {
+        !self.is_solid(&(voxel + Self::FACE_NORMALS[face]))
+    }
+
+    // the whole chunk, solid or not
+    fn chunk_bounds(&self, key: &Point3<i32>) -> Aabb {
+        let min = (key * CHUNK_SIZE).map(|v| v as Precision * self.voxel_size);
+
+        Aabb::new(min, min + Vector3::repeat(CHUNK_SIZE as Precision * self.voxel_size))
+    }
+
+    fn chunk_aabb(&self, key: &Point3<i32>) -> Option<Aabb> {
+        let (min, max) = self.chunks.get(key)?.occupied_bounds()?;
+        let origin = key * CHUNK_SIZE;
+
+        Some(self.voxel_aabb(&(origin + min.coords)).merged(&self.voxel_aabb(&(origin + max.coords))))
+    }
+
+    // pushes only the chunks edited since the last sync to the broadphase
+    pub fn sync_broad_phase(&mut self, broad_phase: &mut BroadPhase, pose: &Isometry3<Precision>) {
+        let mut dirty_chunks = self.dirty_chunks.drain().collect::<Vec<_>>();
+
+        // proxy ids are handed out in insertion order, which has to be the same from run to run
+        dirty_chunks.sort_by(key_order);
+
+        for key in dirty_chunks {
+            let aabb = self.chunk_aabb(&key).map(|aabb| aabb.transform_by(pose));
+
+            match (aabb, self.proxies.get(&key).copied()) {
+                (Some(aabb), Some(proxy)) => broad_phase.update(proxy, aabb),
+                (Some(aabb), None) => { self.proxies.insert(key, broad_phase.insert(aabb)); },
+                (None, Some(proxy)) => {
+                    broad_phase.remove(proxy);
+                    self.proxies.remove(&key);
+                },
+                (None, None) => {}
+            }
+        }
+    }
+
+    pub fn contacts_with_ball(
+        &self,
+        pose: &Isometry3<Precision>,
+        center: &Point3<Precision>,
+        radius: Precision
+    ) -> Vec<Contact> {
+        let mut contacts = Vec::new();
+
+        let local_center = pose.inverse_transform_point(center);
+        let min = self.voxel_at(&(local_center - Vector3::repeat(radius)));
+        let max = self.voxel_at(&(local_center + Vector3::repeat(radius)));
+
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let voxel = Point3::new(x, y, z);
+
+                    if !self.is_solid(&voxel) { continue; }
+
+                    if let Some((point, normal, depth)) = self.ball_voxel_contact(&voxel, &local_center, radius) {
+                        contacts.push(Contact {
+                            point: pose * point,
+                            normal: pose.rotation * normal,
+
+                            depth
+                        });
+                    }
+                }
+            }
+        }
+
+        contacts
+    }
+
+    fn ball_voxel_contact(
+        &self,
+        voxel: &Point3<i32>,
+        center: &Point3<Precision>,
+        radius: Precision
+    ) -> Option<(Point3<Precision>, UnitVector3<Precision>, Precision)> {
+        let aabb = self.voxel_aabb(voxel);
+        let closest = aabb.closest_point(center);
+        let delta = center - closest;
+
+        // voxels own their min faces but not their max faces, so a center exactly on a face shared by two
+        // voxels counts as past the first one and inside the second, and only one of them reports it
+        let side = Vector3::from_fn(|axis, _| if center[axis] >= aabb.max[axis] {
+            1
+        } else if center[axis] < aabb.min[axis] {
+            -1
+        } else {
+            0
+        });
+
+        if side == Vector3::zeros() {
+            // center is inside the voxel, push out through the nearest exposed face
+            return (0..6)
+                .filter(|&face| self.is_face_exposed(voxel, face))
+                .map(|face| {
+                    let axis = face / 2;
+                    let normal = Self::FACE_NORMALS[face].map(|v| v as Precision);
+                    let distance = if face % 2 == 0 {
+                        aabb.max[axis] - center[axis]
+                    } else {
+                        center[axis] - aabb.min[axis]
+                    };
+                    let point = center + normal * distance;
+
+                    (point, UnitVector3::new_unchecked(normal), radius + distance)
+                })
+                .min_by(|a, b| a.2.total_cmp(&b.2));
+        }
+
+        // the ball sits past a face shared with a solid neighbour, so the contact
+        // belongs to that neighbour and would otherwise snag on the internal face
+        for axis in 0..3 {
+            if side[axis] == 0 { continue; }
+
+            let mut neighbour = *voxel;
+
+            neighbour[axis] += side[axis];
+
+            if self.is_solid(&neighbour) { return None; }
+        }
+
+        let distance = delta.norm();
+
+        if distance >= radius { return None; }
+
+        // a center right on an exposed face is pushed straight out of it
+        let normal = if distance > 0.0 {
+            UnitVector3::new_unchecked(delta / distance)
+        } else {
+            UnitVector3::new_normalize(side.map(|v| v as Precision))
+        };
+
+        Some((closest, normal, radius - distance))
+    }
+}
+
+impl Shape for VoxelGrid {
+    fn local_aabb(&self) -> Aabb {
+        self.chunks.keys()
+            .filter_map(|key| self.chunk_aabb(key))
+            .fold(Aabb::EMPTY, |a, b| a.merged(&b))
+    }
+
+    // visits chunks nearest first, so the cost depends on how many chunks are close rather than how far away the point is
+    fn project_local_point(&self, point: &Point3<Precision>) -> PointProjection {
+        let is_inside = self.is_solid(&self.voxel_at(point));
+
+        let mut chunks = self.chunks.iter()
+            .map(|(key, chunk)| ((self.chunk_bounds(key).closest_point(point) - point).norm_squared(), key, chunk))
+            .collect::<Vec<_>>();
+
+        // ties are broken by key so equal inputs always give the same projection
+        chunks.sort_by(|(a_sq, a, _), (b_sq, b, _)| a_sq.total_cmp(b_sq).then(key_order(a, b)));
+
+        let mut best: Option<(Precision, Point3<Precision>)> = None;
+
+        for (chunk_sq, key, chunk) in chunks {
+            // no face in this chunk or any after it can be closer
+            if best.is_some_and(|(best_sq, _)| best_sq <= chunk_sq) { break; }
+
+            let origin = key * CHUNK_SIZE;
+
+            for local in chunk.solid_locals() {
+                let voxel = origin + local.coords;
+                let aabb = self.voxel_aabb(&voxel);
+
+                for face in (0..6).filter(|&face| self.is_face_exposed(&voxel, face)) {
+                    let axis = face / 2;
+                    let mut candidate = aabb.closest_point(point);
+
+                    candidate[axis] = if face % 2 == 0 { aabb.max[axis] } else { aabb.min[axis] };
+
+                    let distance_sq = (candidate - point).norm_squared();
+
+                    if best.is_none_or(|(best_sq, _)| distance_sq < best_sq) {
+                        best = Some((distance_sq, candidate));
+                    }
+                }
+            }
+        }
+
+        PointProjection::new(best.map_or(*point, |(_, candidate)| candidate), is_inside)
+    }
+}
+
+// hash map order changes from run to run, chunks are visited in this order wherever it can affect the result
+#[inline]
+fn key_order(a: &Point3<i32>, b: &Point3<i32>) -> Ordering {
+    (a.x, a.y, a.z).cmp(&(b.x, b.y, b.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn far_points_project_onto_the_nearest_face() {
+        let mut grid = VoxelGrid::new(1.0);
+
+        grid.set_solid(&Point3::new(0, 0, 0), true);
+        grid.set_solid(&Point3::new(1, 0, 0), true);
+        grid.set_solid(&Point3::new(-40, 0, 0), true);
+
+        let projection = grid.project_local_point(&Point3::new(1.0e6, 0.5, 0.5));
+
+        assert!(!projection.is_inside);
+        assert_eq!(projection.point, Point3::new(2.0, 0.5, 0.5));
+
+        let projection = grid.project_local_point(&Point3::new(-1.0e6, 0.25, 0.75));
+
+        assert_eq!(projection.point, Point3::new(-40.0, 0.25, 0.75));
+    }
+
+    #[test]
+    fn inside_points_leave_through_an_exposed_face() {
+        let mut grid = VoxelGrid::new(1.0);
+
+        for x in 0..3 {
+            grid.set_solid(&Point3::new(x, 0, 0), true);
+        }
+
+        let projection = grid.project_local_point(&Point3::new(1.5, 0.5, 0.9));
+
+        assert!(projection.is_inside);
+        assert!((projection.point - Point3::new(1.5, 0.5, 1.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn chunks_get_broad_phase_proxies_in_key_order() {
+        let mut grid = VoxelGrid::new(1.0);
+        let mut broad_phase = BroadPhase::new();
+
+        let mut keys = (0..20).map(|i| Point3::new((i * 7) % 5 - 2, (i * 3) % 4, i % 3)).collect::<Vec<_>>();
+
+        for key in &keys {
+            grid.set_solid(&(key * CHUNK_SIZE), true);
+        }
+
+        grid.sync_broad_phase(&mut broad_phase, &Isometry3::identity());
+
+        keys.sort_by(key_order);
+
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(grid.proxies[key], ProxyId::new(i));
+        }
+    }
+
+    fn floor(grid: &mut VoxelGrid, x: std::ops::Range<i32>, z: std::ops::Range<i32>) {
+        for x in x {
+            for z in z.clone() {
+                grid.set_solid(&Point3::new(x, 0, z), true);
+            }
+        }
+    }
+
+    fn assert_single_floor_contact(grid: &VoxelGrid, center: Point3<Precision>) {
+        let contacts = grid.contacts_with_ball(&Isometry3::identity(), &center, 0.5);
+
+        assert_eq!(contacts.len(), 1, "{contacts:?}");
+        assert!((contacts[0].normal.into_inner() - Vector3::y()).norm() < 1.0e-6);
+        assert!((contacts[0].depth - 0.1).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn balls_on_a_flat_floor_touch_it_once() {
+        let mut grid = VoxelGrid::new(1.0);
+
+        floor(&mut grid, 0..3, 0..3);
+
+        assert_single_floor_contact(&grid, Point3::new(1.5, 1.4, 1.5));
+        assert_single_floor_contact(&grid, Point3::new(1.2, 1.4, 0.7));
+    }
+
+    #[test]
+    fn balls_on_a_seam_touch_only_one_voxel() {
+        let mut grid = VoxelGrid::new(1.0);
+
+        floor(&mut grid, 0..3, 0..3);
+
+        assert_single_floor_contact(&grid, Point3::new(1.0, 1.4, 1.5));
+        assert_single_floor_contact(&grid, Point3::new(1.0, 1.4, 2.0));
+    }
+
+    #[test]
+    fn seams_on_chunk_borders_are_internal_too() {
+        let mut grid = VoxelGrid::new(1.0);
+
+        floor(&mut grid, CHUNK_SIZE - 2..CHUNK_SIZE + 2, -1..1);
+
+        assert_single_floor_contact(&grid, Point3::new(CHUNK_SIZE as Precision, 1.4, 0.0));
+        assert_single_floor_contact(&grid, Point3::new(CHUNK_SIZE as Precision + 0.3, 1.4, -0.2));
+    }
+
+    #[test]
+    fn balls_touching_an_exposed_face_are_pushed_out_of_it() {
+        let mut grid = VoxelGrid::new(1.0);
+
+        grid.set_solid(&Point3::new(0, 0, 0), true);
+
+        let contacts = grid.contacts_with_ball(&Isometry3::identity(), &Point3::new(1.0, 0.5, 0.5), 0.5);
+
+        assert_eq!(contacts.len(), 1);
+        assert_eq!(contacts[0].normal.into_inner(), Vector3::x());
+        assert_eq!(contacts[0].depth, 0.5);
+    }
+}
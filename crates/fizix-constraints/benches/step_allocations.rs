@@ -0,0 +1,114 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use fizix_constraints::{AxisConstraint, DistanceConstraint};
+use fizix_core::{JacobiAveraging, Precision, RigidBodyDesc, SolverMode, World};
+use nalgebra::{Matrix3, Point3, Vector3};
+
+// counts every allocation made by the process, stepping should not add to it once warmed up
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const WARM_UP_STEPS: usize = 10;
+const MEASURED_STEPS: usize = 200;
+
+// a hanging chain of links joined by a distance and an axis constraint each
+fn chain(links: usize, solver_mode: SolverMode) -> World {
+    let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 8, 2);
+
+    world.sleep_settings.enabled = false;
+    world.solver_mode = solver_mode;
+
+    let mut last = world.create_body(RigidBodyDesc::fixed());
+
+    for i in 0..links {
+        let link = world.create_body(RigidBodyDesc::dynamic()
+            .with_position(Point3::new((i + 1) as Precision, 0.0, 0.0))
+            .with_mass(1.0, Matrix3::identity() * (1.0 / 6.0)));
+
+        world.add_constraint(DistanceConstraint {
+            body_a: last,
+            body_b: link,
+
+            rest_length: 1.0,
+
+            ..Default::default()
+        });
+        world.add_constraint(AxisConstraint {
+            body_a: last,
+            body_b: link,
+
+            local_axis_a: Vector3::z_axis(),
+            local_axis_b: Vector3::z_axis(),
+
+            ..Default::default()
+        });
+
+        last = link;
+    }
+
+    world
+}
+
+fn main() {
+    let solver_modes = [
+        SolverMode::GaussSeidel,
+        SolverMode::Coloured,
+        SolverMode::Jacobi(JacobiAveraging::ConstraintCount),
+        SolverMode::Jacobi(JacobiAveraging::MassSplitting)
+    ];
+
+    for (solver_mode, links) in solver_modes.into_iter().flat_map(|mode| [(mode, 10), (mode, 100), (mode, 1000)]) {
+        let mut world = chain(links, solver_mode);
+
+        // the first steps size the scratch buffers
+        for _ in 0..WARM_UP_STEPS {
+            world.step(1.0 / 60.0);
+        }
+
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        let start = Instant::now();
+
+        for _ in 0..MEASURED_STEPS {
+            world.step(1.0 / 60.0);
+        }
+
+        let elapsed = start.elapsed();
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+        println!(
+            "{solver_mode:?}, chain of {links:>4} links: {:>10.3?} per step, {} allocations per step",
+            elapsed / MEASURED_STEPS as u32,
+            allocations as Precision / MEASURED_STEPS as Precision
+        );
+    }
+
+    // the solver itself does not allocate, rayon's shared queue takes a block every 63 jobs handed to the pool
+    if cfg!(feature = "parallel") {
+        println!("scenes large enough to split move onto the thread pool once per sub step, so rayon allocates about sub steps / 63 times per step");
+        println!("the first parallel run also counts the pool threads starting up");
+    }
+}
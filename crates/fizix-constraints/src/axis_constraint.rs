@@ -1,4 +1,4 @@
-use fizix_core::{BodyHandle, BodySet, Constraint, CorrectionData, Precision, EPSILON};
+use fizix_core::{math, smallvec, BodyHandle, BodySet, BodyVec, Constraint, CorrectionData, Precision, EPSILON};
 use nalgebra::{UnitVector3, Vector3};
 
 pub struct AxisConstraint {
@@ -26,12 +26,13 @@ impl Default for AxisConstraint {
 }
 
 impl Constraint for AxisConstraint {
-    fn compute_correction(&self, bodies: &BodySet) -> Option<CorrectionData> {
-        let body_a = *self.body_a;
-        let body_b = *self.body_b;
+    fn bodies(&self) -> BodyVec<BodyHandle> {
+        smallvec![self.body_a, self.body_b]
+    }
 
-        let orient_a = bodies.orientation[body_a];
-        let orient_b = bodies.orientation[body_b];
+    fn compute_correction(&self, bodies: &BodySet) -> Option<CorrectionData> {
+        let orient_a = bodies.orientation(self.body_a)?;
+        let orient_b = bodies.orientation(self.body_b)?;
 
         // world space axes
         let u_a = orient_a.transform_vector(&self.local_axis_a);
@@ -41,15 +42,15 @@ impl Constraint for AxisConstraint {
 
         let sin_theta = orthogonal.norm();
         let cos_theta = u_a.dot(&u_b);
-        let phi = sin_theta.atan2(cos_theta);
+        let phi = math::atan2(sin_theta, cos_theta);
 
         if phi.abs() < EPSILON { return None; }
 
         let axis = UnitVector3::new_unchecked(orthogonal / sin_theta);
 
         Some(CorrectionData::Rotational {
-            handles: vec![self.body_a, self.body_b],
-            axes: vec![-axis, axis],
+            handles: smallvec![self.body_a, self.body_b],
+            axes: smallvec![-axis, axis],
 
             error: phi,
             alpha: self.compliance
@@ -1,4 +1,4 @@
-use fizix_core::{BodyHandle, BodySet, Constraint, CorrectionData, Precision, EPSILON, EPSILON_SQUARED};
+use fizix_core::{smallvec, BodyHandle, BodySet, BodyVec, Constraint, CorrectionData, Precision, EPSILON, EPSILON_SQUARED};
 use nalgebra::{Point3, UnitVector3};
 
 pub struct DistanceConstraint {
@@ -10,7 +10,7 @@ pub struct DistanceConstraint {
 
     pub rest_length: Precision,
 
-    pub compliance: Precision, // inverse stiffness
+    pub compliance: Precision // inverse stiffness
 }
 
 impl Default for DistanceConstraint {
@@ -30,23 +30,24 @@ impl Default for DistanceConstraint {
 }
 
 impl Constraint for DistanceConstraint {
+    fn bodies(&self) -> BodyVec<BodyHandle> {
+        smallvec![self.body_a, self.body_b]
+    }
+
     fn compute_correction(&self, bodies: &BodySet) -> Option<CorrectionData> {
+        // the raw index accessors below would read whatever body reused a stale slot
+        if !bodies.contains(self.body_a) || !bodies.contains(self.body_b) { return None; }
+
         let body_a = *self.body_a;
         let body_b = *self.body_b;
 
-        let pos_a = bodies.position[body_a];
-        let pos_b = bodies.position[body_b];
-
-        let orient_a = bodies.orientation[body_a];
-        let orient_b = bodies.orientation[body_b];
-
-        // relative space points
-        let r_a = orient_a.transform_point(&self.local_point_a);
-        let r_b = orient_b.transform_point(&self.local_point_b);
-
         // absolute space points
-        let p_a = pos_a + r_a.coords;
-        let p_b = pos_b + r_b.coords;
+        let p_a = bodies.world_point(body_a, &self.local_point_a);
+        let p_b = bodies.world_point(body_b, &self.local_point_b);
+
+        // relative to the centres of mass
+        let r_a = Point3::from(p_a - bodies.center_of_mass(body_a));
+        let r_b = Point3::from(p_b - bodies.center_of_mass(body_b));
 
         let difference = p_b - p_a;
         let distance_sq = difference.norm_squared();
@@ -61,11 +62,69 @@ impl Constraint for DistanceConstraint {
         let normal = UnitVector3::new_unchecked(difference / distance);
 
         Some(CorrectionData::Translational {
-            handles: vec![self.body_a, self.body_b],
-            relative_points: vec![r_a, r_b],
-            normals: vec![-normal, normal],
+            handles: smallvec![self.body_a, self.body_b],
+            relative_points: smallvec![r_a, r_b],
+            normals: smallvec![-normal, normal],
             
             error, alpha: self.compliance
         })
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use fizix_core::{LockedAxes, RigidBodyDesc, World};
+    use nalgebra::{Vector2, Vector3};
+    use super::*;
+
+    #[test]
+    fn stale_bodies_are_not_pulled_on() {
+        let mut world = World::new(Vector3::zeros(), 1, 1);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let removed = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(1.0, 0.0, 0.0)));
+
+        world.remove_body(removed);
+
+        let reused = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(5.0, 0.0, 0.0)));
+        assert_eq!(*reused, *removed);
+
+        let constraint = DistanceConstraint { body_a: anchor, body_b: removed, rest_length: 1.0, ..Default::default() };
+
+        assert!(constraint.compute_correction(world.bodies()).is_none());
+    }
+
+    #[test]
+    fn locked_axes_hold_through_joint_corrections() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 4, 1);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let body = world.create_body(RigidBodyDesc::dynamic()
+            .with_position(Point3::new(1.0, -1.0, 0.5))
+            .with_locked_axes(LockedAxes::PLANAR_XY));
+
+        // off centre on every axis, so the joint pulls along z and twists about x and y as well
+        world.add_constraint(DistanceConstraint {
+            body_a: anchor,
+            body_b: body,
+            local_point_b: Point3::new(0.3, 0.2, 0.1),
+            rest_length: 1.5,
+            ..Default::default()
+        }).unwrap();
+
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+
+            let bodies = world.bodies();
+            let orientation = bodies.orientation(body).unwrap();
+
+            assert_eq!(bodies.position(body).unwrap().z, 0.5);
+            assert_eq!(bodies.linear_velocity(body).unwrap().z, 0.0);
+            assert_eq!(bodies.angular_velocity(body).unwrap().xy(), Vector2::zeros());
+            assert_eq!((orientation.i, orientation.j), (0.0, 0.0));
+        }
+
+        // the free axes still swing
+        assert!((world.bodies().position(body).unwrap().x - 1.0).abs() > 0.1);
+        assert!(world.bodies().angular_velocity(body).unwrap().z != 0.0);
+    }
+}
@@ -1,4 +1,4 @@
-use fizix_core::{BodyHandle, BodySet, Constraint, CorrectionData, Precision, EPSILON_SQUARED};
+use fizix_core::{smallvec, BodyHandle, BodySet, BodyVec, Constraint, CorrectionData, Precision, EPSILON_SQUARED};
 use nalgebra::{Point3, UnitVector3, Vector3};
 
 pub struct LinearConstraint {
@@ -37,26 +37,29 @@ impl Default for LinearConstraint {
 }
 
 impl Constraint for LinearConstraint {
+    fn bodies(&self) -> BodyVec<BodyHandle> {
+        smallvec![self.body_a, self.body_b]
+    }
+
     fn compute_correction(&self, bodies: &BodySet) -> Option<CorrectionData> {
+        // the raw index accessors below would read whatever body reused a stale slot
+        if !bodies.contains(self.body_a) || !bodies.contains(self.body_b) { return None; }
+
         let body_a = *self.body_a;
         let body_b = *self.body_b;
 
-        let pos_a = bodies.position[body_a];
-        let pos_b = bodies.position[body_b];
+        let orient_a = bodies.orientation(self.body_a)?;
 
-        let orient_a = bodies.orientation[body_a];
-        let orient_b = bodies.orientation[body_b];
+        // absolute space points
+        let p_a = bodies.world_point(body_a, &self.local_point_a);
+        let p_b = bodies.world_point(body_b, &self.local_point_b);
 
-        // relative space points
-        let r_a = orient_a.transform_point(&self.local_point_a);
-        let r_b = orient_b.transform_point(&self.local_point_b);
+        // relative to the centres of mass
+        let r_a = Point3::from(p_a - bodies.center_of_mass(body_a));
+        let r_b = Point3::from(p_b - bodies.center_of_mass(body_b));
 
         let axis = orient_a.transform_vector(&self.local_axis);
 
-        // absolute space points
-        let p_a = pos_a + r_a.coords;
-        let p_b = pos_b + r_b.coords;
-
         let difference = p_b - p_a;
         let d_dot = difference.dot(&axis).clamp(self.min_distance, self.max_distance);
 
@@ -70,12 +73,35 @@ impl Constraint for LinearConstraint {
         let normal = UnitVector3::new_unchecked(p_prime / distance);
 
         Some(CorrectionData::Translational {
-            handles: vec![self.body_a, self.body_b],
-            relative_points: vec![r_a, r_b],
-            normals: vec![-normal, normal],
+            handles: smallvec![self.body_a, self.body_b],
+            relative_points: smallvec![r_a, r_b],
+            normals: smallvec![-normal, normal],
             
             error: distance,
             alpha: self.compliance
         })
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use fizix_core::{RigidBodyDesc, World};
+    use super::*;
+
+    #[test]
+    fn stale_bodies_are_not_pulled_on() {
+        let mut world = World::new(Vector3::zeros(), 1, 1);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let removed = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(1.0, 1.0, 0.0)));
+
+        world.remove_body(removed);
+
+        let reused = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(5.0, 5.0, 0.0)));
+        assert_eq!(*reused, *removed);
+
+        let constraint = LinearConstraint { body_a: anchor, body_b: removed, ..Default::default() };
+
+        assert!(constraint.compute_correction(world.bodies()).is_none());
+        assert!(LinearConstraint { body_b: reused, ..constraint }.compute_correction(world.bodies()).is_some());
+    }
+}
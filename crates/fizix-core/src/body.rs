@@ -1,16 +1,25 @@
-use std::ops::Deref;
+use std::ops::{BitOr, BitOrAssign, Deref};
 
-use crate::Precision;
-use nalgebra::{Matrix3, Point3, UnitQuaternion, Vector3};
+use crate::{math, Precision};
+use nalgebra::{Isometry3, Matrix3, Point3, Translation3, UnitQuaternion, Vector3};
 
+// index into the body set plus the generation of the slot, so handles to removed bodies go stale
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub struct BodyHandle(usize);
+pub struct BodyHandle {
+    index: usize,
+    generation: u32
+}
 
 impl BodyHandle {
-    pub const INVALID: Self = Self(usize::MAX);
+    pub const INVALID: Self = Self { index: usize::MAX, generation: u32::MAX };
+
+    pub fn new(index: usize, generation: u32) -> Self {
+        Self { index, generation }
+    }
 
-    pub fn new(index: usize) -> Self {
-        Self(index)
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.generation
     }
 }
 
@@ -18,29 +27,156 @@ impl Deref for BodyHandle {
     type Target = usize;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.index
+    }
+}
+
+// position change of a body from one constraint correction, the rotation is a scaled axis about the centre of mass
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BodyDelta {
+    pub body: usize,
+
+    pub translation: Vector3<Precision>,
+    pub rotation: Vector3<Precision>
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BodyType {
+    Dynamic,
+    Static,
+    Kinematic // moved by the user, never by the solver
+}
+
+// linear terms decay speed exponentially, quadratic terms act like air drag
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Damping {
+    pub linear: Precision,
+    pub angular: Precision,
+
+    pub linear_quadratic: Precision,
+    pub angular_quadratic: Precision
+}
+
+impl Damping {
+    pub fn new(linear: Precision, angular: Precision) -> Self {
+        Self { linear, angular, ..Default::default() }
     }
 }
 
+// world space axes a body may not translate along or rotate about
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct LockedAxes(u8);
+
+impl LockedAxes {
+    pub const NONE: Self = Self(0);
+
+    pub const TRANSLATION_X: Self = Self(1 << 0);
+    pub const TRANSLATION_Y: Self = Self(1 << 1);
+    pub const TRANSLATION_Z: Self = Self(1 << 2);
+
+    pub const ROTATION_X: Self = Self(1 << 3);
+    pub const ROTATION_Y: Self = Self(1 << 4);
+    pub const ROTATION_Z: Self = Self(1 << 5);
+
+    pub const TRANSLATION: Self = Self(0b000111);
+    pub const ROTATION: Self = Self(0b111000);
+    pub const ALL: Self = Self(0b111111);
+
+    // moves in the xy plane and only turns about z, for side scrollers
+    pub const PLANAR_XY: Self = Self(Self::TRANSLATION_Z.0 | Self::ROTATION_X.0 | Self::ROTATION_Y.0);
+
+    #[inline]
+    pub fn contains(&self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    // 1 for free axes, 0 for locked ones
+    #[inline]
+    pub fn translation_mask(&self) -> Vector3<Precision> {
+        Vector3::new(
+            if self.contains(Self::TRANSLATION_X) { 0.0 } else { 1.0 },
+            if self.contains(Self::TRANSLATION_Y) { 0.0 } else { 1.0 },
+            if self.contains(Self::TRANSLATION_Z) { 0.0 } else { 1.0 }
+        )
+    }
+
+    #[inline]
+    pub fn rotation_mask(&self) -> Vector3<Precision> {
+        Vector3::new(
+            if self.contains(Self::ROTATION_X) { 0.0 } else { 1.0 },
+            if self.contains(Self::ROTATION_Y) { 0.0 } else { 1.0 },
+            if self.contains(Self::ROTATION_Z) { 0.0 } else { 1.0 }
+        )
+    }
+}
+
+impl BitOr for LockedAxes {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for LockedAxes {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GyroscopicMode {
+    Disabled, // angular velocity only changes through torques and constraints
+    Explicit, // gains energy at high spin rates
+    #[default]
+    Implicit // one newton step of the backward euler update, stays stable at high spin rates
+}
+
+// per body state by slot, only reachable through handles outside the crate so removed slots stay hidden
 #[derive(Default)]
 pub struct BodySet {
-    pub position: Vec<Point3<Precision>>,
-    pub orientation: Vec<UnitQuaternion<Precision>>,
+    pub(crate) position: Vec<Point3<Precision>>,
+    pub(crate) orientation: Vec<UnitQuaternion<Precision>>,
+
+    pub(crate) last_position: Vec<Point3<Precision>>,
+    pub(crate) last_orientation: Vec<UnitQuaternion<Precision>>,
+
+    pub(crate) linear_velocity: Vec<Vector3<Precision>>,
+    pub(crate) angular_velocity: Vec<Vector3<Precision>>,
+
+    pub(crate) force: Vec<Vector3<Precision>>,
+    pub(crate) torque: Vec<Vector3<Precision>>,
+
+    pub(crate) inverse_mass: Vec<Precision>,
+    pub(crate) inverse_inertia_tensor_local: Vec<Matrix3<Precision>>,
+    pub(crate) inertia_tensor_local: Vec<Matrix3<Precision>>, // zero when the inverse is singular
+    pub(crate) local_center_of_mass: Vec<Vector3<Precision>>, // offset from the body origin, in body space
+    pub(crate) locked_axes: Vec<LockedAxes>,
+    pub(crate) gravity_scale: Vec<Precision>,
+    pub(crate) ccd_enabled: Vec<bool>,
 
-    pub last_position: Vec<Point3<Precision>>,
-    pub last_orientation: Vec<UnitQuaternion<Precision>>,
+    pub(crate) damping: Vec<Damping>,
 
-    pub linear_velocity: Vec<Vector3<Precision>>,
-    pub angular_velocity: Vec<Vector3<Precision>>,
+    pub(crate) body_type: Vec<BodyType>,
+    pub(crate) kinematic_target: Vec<Option<(Point3<Precision>, UnitQuaternion<Precision>)>>,
 
-    pub force: Vec<Vector3<Precision>>,
-    pub torque: Vec<Vector3<Precision>>,
+    pub(crate) sleeping: Vec<bool>,
+    pub(crate) sleep_timer: Vec<Precision>, // time spent under the sleep thresholds
 
-    pub inverse_mass: Vec<Precision>,
-    pub inverse_inertia_tensor_local: Vec<Matrix3<Precision>>,
+    pub(crate) user_data: Vec<u64>, // opaque to the engine
 
     // derived data
-    pub inverse_inertia_tensor_world: Vec<Matrix3<Precision>>,
+    pub(crate) inverse_inertia_tensor_world: Vec<Matrix3<Precision>>,
+
+    // slot bookkeeping, removed slots stay in the vectors until reused
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free_list: Vec<usize>
 }
 
 impl BodySet {
@@ -49,17 +185,355 @@ impl BodySet {
         Self::default()
     }
 
+    pub(crate) fn insert(
+        &mut self,
+        position: Point3<Precision>,
+        orientation: UnitQuaternion<Precision>,
+        inverse_mass: Precision,
+        inverse_inertia_tensor_local: Matrix3<Precision>
+    ) -> BodyHandle {
+        let rotation = orientation.to_rotation_matrix();
+        let inverse_inertia_tensor_world = rotation * inverse_inertia_tensor_local * rotation.transpose();
+        let body_type = if inverse_mass > 0.0 { BodyType::Dynamic } else { BodyType::Static };
+        let inertia_tensor_local = inverse_inertia_tensor_local.try_inverse().unwrap_or(Matrix3::zeros());
+
+        if let Some(i) = self.free_list.pop() {
+            self.position[i] = position;
+            self.orientation[i] = orientation;
+
+            self.last_position[i] = position;
+            self.last_orientation[i] = orientation;
+
+            self.linear_velocity[i] = Vector3::zeros();
+            self.angular_velocity[i] = Vector3::zeros();
+
+            self.force[i] = Vector3::zeros();
+            self.torque[i] = Vector3::zeros();
+
+            self.inverse_mass[i] = inverse_mass;
+            self.inverse_inertia_tensor_local[i] = inverse_inertia_tensor_local;
+            self.inertia_tensor_local[i] = inertia_tensor_local;
+            self.local_center_of_mass[i] = Vector3::zeros();
+            self.locked_axes[i] = LockedAxes::NONE;
+            self.gravity_scale[i] = 1.0;
+            self.ccd_enabled[i] = false;
+
+            self.damping[i] = Damping::default();
+
+            self.body_type[i] = body_type;
+            self.kinematic_target[i] = None;
+
+            self.sleeping[i] = false;
+            self.sleep_timer[i] = 0.0;
+
+            self.user_data[i] = 0;
+
+            self.inverse_inertia_tensor_world[i] = inverse_inertia_tensor_world;
+
+            self.alive[i] = true;
+
+            return BodyHandle::new(i, self.generations[i]);
+        }
+
+        self.position.push(position);
+        self.orientation.push(orientation);
+
+        self.last_position.push(position);
+        self.last_orientation.push(orientation);
+
+        self.linear_velocity.push(Vector3::zeros());
+        self.angular_velocity.push(Vector3::zeros());
+
+        self.force.push(Vector3::zeros());
+        self.torque.push(Vector3::zeros());
+
+        self.inverse_mass.push(inverse_mass);
+        self.inverse_inertia_tensor_local.push(inverse_inertia_tensor_local);
+        self.inertia_tensor_local.push(inertia_tensor_local);
+        self.local_center_of_mass.push(Vector3::zeros());
+        self.locked_axes.push(LockedAxes::NONE);
+        self.gravity_scale.push(1.0);
+        self.ccd_enabled.push(false);
+
+        self.damping.push(Damping::default());
+
+        self.body_type.push(body_type);
+        self.kinematic_target.push(None);
+
+        self.sleeping.push(false);
+        self.sleep_timer.push(0.0);
+
+        self.user_data.push(0);
+
+        self.inverse_inertia_tensor_world.push(inverse_inertia_tensor_world);
+
+        self.generations.push(0);
+        self.alive.push(true);
+
+        BodyHandle::new(self.position.len() - 1, 0)
+    }
+
+    pub(crate) fn remove(&mut self, handle: BodyHandle) -> bool {
+        if !self.contains(handle) { return false; }
+
+        let i = *handle;
+
+        // the dead slot must not be integrated or pull on anything while it waits for reuse
+        self.inverse_mass[i] = 0.0;
+        self.inverse_inertia_tensor_local[i] = Matrix3::zeros();
+        self.inertia_tensor_local[i] = Matrix3::zeros();
+        self.inverse_inertia_tensor_world[i] = Matrix3::zeros();
+
+        self.linear_velocity[i] = Vector3::zeros();
+        self.angular_velocity[i] = Vector3::zeros();
+
+        self.body_type[i] = BodyType::Static;
+        self.kinematic_target[i] = None;
+
+        self.sleeping[i] = false;
+        self.sleep_timer[i] = 0.0;
+
+        self.generations[i] = self.generations[i].wrapping_add(1);
+        self.alive[i] = false;
+        self.free_list.push(i);
+
+        true
+    }
+
+    #[inline]
+    pub fn contains(&self, handle: BodyHandle) -> bool {
+        self.generations.get(*handle).is_some_and(|&g| g == handle.generation) && self.alive[*handle]
+    }
+
+    #[inline]
+    pub fn is_alive(&self, i: usize) -> bool {
+        self.alive[i]
+    }
+
+    // number of live bodies, slots are counted by position.len()
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.alive.len() - self.free_list.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn handle(&self, i: usize) -> BodyHandle {
+        BodyHandle::new(i, self.generations[i])
+    }
+
+    pub fn handles(&self) -> impl Iterator<Item = BodyHandle> + '_ {
+        (0..self.alive.len())
+            .filter(|&i| self.alive[i])
+            .map(|i| self.handle(i))
+    }
+
+    // the following return None for stale handles
+
+    #[inline]
+    pub fn position(&self, handle: BodyHandle) -> Option<Point3<Precision>> {
+        self.contains(handle).then(|| self.position[*handle])
+    }
+
+    #[inline]
+    pub fn orientation(&self, handle: BodyHandle) -> Option<UnitQuaternion<Precision>> {
+        self.contains(handle).then(|| self.orientation[*handle])
+    }
+
+    #[inline]
+    pub fn pose(&self, handle: BodyHandle) -> Option<Isometry3<Precision>> {
+        self.contains(handle).then(|| Isometry3::from_parts(Translation3::from(self.position[*handle]), self.orientation[*handle]))
+    }
+
+    #[inline]
+    pub fn linear_velocity(&self, handle: BodyHandle) -> Option<Vector3<Precision>> {
+        self.contains(handle).then(|| self.linear_velocity[*handle])
+    }
+
+    #[inline]
+    pub fn angular_velocity(&self, handle: BodyHandle) -> Option<Vector3<Precision>> {
+        self.contains(handle).then(|| self.angular_velocity[*handle])
+    }
+
+    #[inline]
+    pub fn body_type(&self, handle: BodyHandle) -> Option<BodyType> {
+        self.contains(handle).then(|| self.body_type[*handle])
+    }
+
+    #[inline]
+    pub fn is_ccd_enabled(&self, handle: BodyHandle) -> Option<bool> {
+        self.contains(handle).then(|| self.ccd_enabled[*handle])
+    }
+
+    // only dynamic bodies respond to the solver, kinematic and static ones act as infinitely heavy
     #[inline]
     pub fn has_finite_mass(&self, i: usize) -> bool {
-        self.inverse_mass[i] > 0.0
+        self.body_type[i] == BodyType::Dynamic && self.inverse_mass[i] > 0.0
+    }
+
+    #[inline]
+    pub fn is_kinematic(&self, i: usize) -> bool {
+        self.body_type[i] == BodyType::Kinematic
     }
 
+    #[inline]
+    pub fn is_sleeping(&self, i: usize) -> bool {
+        self.sleeping[i]
+    }
+
+    #[inline]
+    pub fn wake(&mut self, i: usize) {
+        self.sleeping[i] = false;
+        self.sleep_timer[i] = 0.0;
+    }
+
+    // whether a kinematic body will move during the next step
+    pub fn is_kinematic_moving(&self, i: usize) -> bool {
+        if !self.is_kinematic(i) { return false; }
+
+        match self.kinematic_target[i] {
+            Some((position, orientation)) => position != self.position[i] || orientation != self.orientation[i],
+            None => self.linear_velocity[i] != Vector3::zeros() || self.angular_velocity[i] != Vector3::zeros()
+        }
+    }
+
+    // inverse mass per world axis, zero along locked axes
+    #[inline]
+    pub fn effective_inverse_mass(&self, i: usize) -> Vector3<Precision> {
+        if !self.has_finite_mass(i) { return Vector3::zeros(); }
+
+        self.locked_axes[i].translation_mask() * self.inverse_mass[i]
+    }
+
+    // world inverse inertia with the rows and columns of locked axes zeroed
+    #[inline]
+    pub fn effective_inverse_inertia_tensor(&self, i: usize) -> Matrix3<Precision> {
+        if !self.has_finite_mass(i) { return Matrix3::zeros(); }
+
+        let locks = self.locked_axes[i];
+
+        if locks.is_empty() { return self.inverse_inertia_tensor_world[i]; }
+
+        let mask = Matrix3::from_diagonal(&locks.rotation_mask());
+
+        mask * self.inverse_inertia_tensor_world[i] * mask
+    }
+
+    #[inline]
+    pub fn apply_axis_locks(&mut self, i: usize) {
+        let locks = self.locked_axes[i];
+
+        if locks.is_empty() { return; }
+
+        self.linear_velocity[i].component_mul_assign(&locks.translation_mask());
+        self.angular_velocity[i].component_mul_assign(&locks.rotation_mask());
+    }
+
+    pub fn apply_damping(&mut self, i: usize, dt: Precision) {
+        let damping = self.damping[i];
+
+        damp(&mut self.linear_velocity[i], damping.linear, damping.linear_quadratic, dt);
+        damp(&mut self.angular_velocity[i], damping.angular, damping.angular_quadratic, dt);
+    }
+
+    // accounts for the -w x Iw term of euler's equations, solved in body space where the inertia is constant
+    pub fn apply_gyroscopic_torque(&mut self, i: usize, mode: GyroscopicMode, dt: Precision) {
+        if mode == GyroscopicMode::Disabled { return; }
+
+        let inertia = self.inertia_tensor_local[i];
+
+        let orientation = self.orientation[i];
+        let omega = orientation.inverse_transform_vector(&self.angular_velocity[i]);
+        let momentum = inertia * omega;
+
+        let omega = match mode {
+            GyroscopicMode::Explicit => omega - self.inverse_inertia_tensor_local[i] * omega.cross(&momentum) * dt,
+            _ => {
+                let residual = omega.cross(&momentum) * dt;
+                let jacobian = inertia + (omega.cross_matrix() * inertia - momentum.cross_matrix()) * dt;
+
+                match jacobian.try_inverse() {
+                    Some(inverse_jacobian) => omega - inverse_jacobian * residual,
+                    None => omega
+                }
+            }
+        };
+
+        self.angular_velocity[i] = orientation.transform_vector(&omega);
+    }
+
+    #[inline]
+    pub fn center_of_mass(&self, i: usize) -> Point3<Precision> {
+        self.position[i] + self.orientation[i] * self.local_center_of_mass[i]
+    }
+
+    #[inline]
+    pub fn last_center_of_mass(&self, i: usize) -> Point3<Precision> {
+        self.last_position[i] + self.last_orientation[i] * self.local_center_of_mass[i]
+    }
+
+    // body space point relative to the origin, to world space
+    #[inline]
+    pub fn world_point(&self, i: usize, local_point: &Point3<Precision>) -> Point3<Precision> {
+        self.position[i] + self.orientation[i] * local_point.coords
+    }
+
+    // rotates about the centre of mass, moving the origin with it
     pub fn apply_rotation_delta(&mut self, i: usize, rotation: Vector3<Precision>) {
         let q = self.orientation[i];
+        let center_of_mass = self.center_of_mass(i);
 
-        self.orientation[i] = UnitQuaternion::from_scaled_axis(rotation) * q;
+        self.orientation[i] = math::quaternion_from_scaled_axis(&rotation) * q;
 
         self.orientation[i].renormalize();
+
+        self.position[i] = center_of_mass - self.orientation[i] * self.local_center_of_mass[i];
+    }
+
+    // fnv-1a over the exact bits of every live body's state, stable across runs and platforms
+    pub fn state_hash(&self) -> u64 {
+        let mut hash = StateHasher::new();
+
+        for i in (0..self.position.len()).filter(|&i| self.alive[i]) {
+            hash.write(i as u64);
+            hash.write(self.generations[i] as u64);
+
+            let vectors = [
+                self.position[i].coords,
+                self.last_position[i].coords,
+                self.linear_velocity[i],
+                self.angular_velocity[i],
+                self.force[i],
+                self.torque[i]
+            ];
+
+            for value in vectors.iter().flatten() {
+                hash.write_float(*value);
+            }
+
+            for value in self.orientation[i].coords.iter().chain(self.last_orientation[i].coords.iter()) {
+                hash.write_float(*value);
+            }
+
+            hash.write(self.body_type[i] as u64);
+            hash.write(self.sleeping[i] as u64);
+            hash.write_float(self.sleep_timer[i]);
+        }
+
+        hash.finish()
+    }
+
+    pub fn apply_delta(&mut self, delta: &BodyDelta) {
+        let i = delta.body;
+
+        self.position[i] += delta.translation;
+
+        self.apply_rotation_delta(i, delta.rotation);
+        self.update_derived_data(i);
     }
 
     pub fn update_derived_data(&mut self, i: usize) {
@@ -67,4 +541,185 @@ impl BodySet {
         
         self.inverse_inertia_tensor_world[i] = rot * self.inverse_inertia_tensor_local[i] * rot.transpose();
     }
-}
\ No newline at end of file
+}
+
+struct StateHasher(u64);
+
+impl StateHasher {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+
+    #[inline]
+    fn write(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    #[inline]
+    fn write_float(&mut self, value: Precision) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+// exact solution of dv/dt = -c v - k |v| v, the direction stays put and the speed follows
+// s(t) = s0 e^(-ct) / (1 + k s0 (1 - e^(-ct)) / c), so splitting a step into sub steps gives the same result
+fn damp(velocity: &mut Vector3<Precision>, linear: Precision, quadratic: Precision, dt: Precision) {
+    if linear <= 0.0 && quadratic <= 0.0 { return; }
+
+    let decay = math::exp(-linear.max(0.0) * dt);
+
+    // time integral of e^(-ct), the quadratic term acts for this long at the starting speed
+    let falloff = if linear > 0.0 { (1.0 - decay) / linear } else { dt };
+
+    *velocity *= decay / (1.0 + quadratic.max(0.0) * velocity.norm() * falloff);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_slots_are_reused_with_a_new_generation() {
+        let mut bodies = BodySet::new();
+
+        let a = bodies.insert(Point3::new(1.0, 0.0, 0.0), UnitQuaternion::identity(), 1.0, Matrix3::identity());
+        let b = bodies.insert(Point3::new(2.0, 0.0, 0.0), UnitQuaternion::identity(), 1.0, Matrix3::identity());
+
+        assert!(bodies.remove(a));
+        assert!(!bodies.remove(a));
+        assert!(!bodies.contains(a));
+        assert_eq!(bodies.len(), 1);
+
+        let c = bodies.insert(Point3::new(3.0, 0.0, 0.0), UnitQuaternion::identity(), 1.0, Matrix3::identity());
+
+        assert_eq!(*c, *a);
+        assert_ne!(c.generation(), a.generation());
+        assert_eq!(bodies.len(), 2);
+
+        // the stale handle must not alias the body that took over its slot
+        assert!(!bodies.contains(a));
+        assert_eq!(bodies.position(a), None);
+        assert_eq!(bodies.position(c), Some(Point3::new(3.0, 0.0, 0.0)));
+        assert_eq!(bodies.position(b), Some(Point3::new(2.0, 0.0, 0.0)));
+
+        assert_eq!(bodies.handles().collect::<Vec<_>>(), vec![c, b]);
+    }
+
+    // free asymmetric body spinning mostly about its unstable middle axis
+    fn spin(mode: GyroscopicMode) -> (Vector3<Precision>, Vector3<Precision>, Precision, Precision) {
+        use crate::{RigidBodyDesc, World};
+
+        let inertia = Matrix3::from_diagonal(&Vector3::new(1.0, 2.0, 3.0));
+        let mut world = World::new(Vector3::zeros(), 4, 1);
+
+        world.gyroscopic_mode = mode;
+
+        let body = world.create_body(RigidBodyDesc::dynamic()
+            .with_mass(1.0, inertia)
+            .with_angular_velocity(Vector3::new(0.01, 10.0, 0.01)));
+
+        let energy = |world: &World| {
+            let omega = world.bodies.orientation(body).unwrap().inverse_transform_vector(&world.bodies.angular_velocity(body).unwrap());
+
+            0.5 * omega.dot(&(inertia * omega))
+        };
+
+        let start_energy = energy(&world);
+        let start = world.bodies.angular_velocity(body).unwrap();
+
+        for _ in 0..600 {
+            world.step(1.0 / 60.0);
+        }
+
+        (start, world.bodies.angular_velocity(body).unwrap(), start_energy, energy(&world))
+    }
+
+    #[test]
+    fn spinning_bodies_tumble_by_default() {
+        let (start, end, ..) = spin(GyroscopicMode::default());
+
+        assert!((end - start).norm() > 1.0);
+    }
+
+    #[test]
+    fn disabled_gyroscopic_torque_keeps_the_spin() {
+        let (start, end, ..) = spin(GyroscopicMode::Disabled);
+
+        assert!((end - start).norm() < 0.05);
+    }
+
+    #[test]
+    fn implicit_gyroscopic_torque_does_not_gain_energy() {
+        let (start, end, start_energy, end_energy) = spin(GyroscopicMode::Implicit);
+
+        // the middle axis is unstable, so the spin has to wander off it
+        assert!((end - start).norm() > 1.0);
+        assert!(end_energy <= start_energy * (1.0 + 1e-6));
+    }
+
+    #[test]
+    fn free_bodies_spin_about_their_center_of_mass() {
+        use crate::{RigidBodyDesc, World};
+
+        let mut world = World::new(Vector3::zeros(), 4, 1);
+
+        let body = world.create_body(RigidBodyDesc::dynamic()
+            .with_center_of_mass(Vector3::new(1.0, 0.0, 0.0))
+            .with_angular_velocity(Vector3::new(0.5, 0.0, 3.0)));
+
+        let center = world.bodies.center_of_mass(*body);
+
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+
+            assert!((world.bodies.center_of_mass(*body) - center).norm() < Precision::EPSILON.sqrt());
+        }
+
+        // the origin orbits the centre instead of the other way round
+        assert!(world.bodies.position(body).unwrap().coords.norm() > 0.5);
+    }
+
+    fn damped_speed(sub_steps: usize) -> Precision {
+        use crate::{RigidBodyDesc, World};
+
+        let mut world = World::new(Vector3::zeros(), sub_steps, 1);
+
+        let body = world.create_body(RigidBodyDesc::dynamic()
+            .with_linear_velocity(Vector3::new(4.0, 0.0, 0.0))
+            .with_damping(Damping { linear: 0.3, linear_quadratic: 0.2, ..Default::default() }));
+
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+        }
+
+        world.bodies.linear_velocity(body).unwrap().norm()
+    }
+
+    #[test]
+    fn damping_does_not_depend_on_sub_steps() {
+        // s0 e^(-c) / (1 + k s0 (1 - e^(-c)) / c) after one second
+        let decay = (-0.3 as Precision).exp();
+        let expected = 4.0 * decay / (1.0 + 0.2 * 4.0 * (1.0 - decay) / 0.3);
+
+        // velocities are read back from positions, which f32 rounds over many small sub steps
+        let tolerance = Precision::EPSILON.sqrt() * 10.0;
+
+        for sub_steps in [1, 4, 16] {
+            assert!((damped_speed(sub_steps) - expected).abs() < tolerance, "{sub_steps} sub steps: {}", damped_speed(sub_steps));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{BodyType, Damping, LockedAxes, Precision};
+use nalgebra::{Matrix3, Point3, UnitQuaternion, Vector3};
+
+// everything needed to create a body, defaults to a unit mass dynamic body at rest at the origin
+// colliders are attached through RigidBodyBuilder in fizix-collisions
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RigidBodyDesc {
+    pub position: Point3<Precision>,
+    pub orientation: UnitQuaternion<Precision>,
+
+    pub linear_velocity: Vector3<Precision>,
+    pub angular_velocity: Vector3<Precision>,
+
+    pub mass: Precision, // ignored unless the body is dynamic
+    pub inertia_tensor: Matrix3<Precision>,
+    pub local_center_of_mass: Vector3<Precision>,
+    pub locked_axes: LockedAxes,
+    pub gravity_scale: Precision,
+    pub ccd_enabled: bool, // colliders sweep their bounds over the coming step, see Collider::compute_swept_aabb
+
+    pub damping: Option<Damping>, // None takes the world default
+
+    pub body_type: BodyType,
+    pub sleeping: bool,
+
+    pub user_data: u64
+}
+
+impl Default for RigidBodyDesc {
+    fn default() -> Self {
+        Self {
+            position: Point3::origin(),
+            orientation: UnitQuaternion::identity(),
+
+            linear_velocity: Vector3::zeros(),
+            angular_velocity: Vector3::zeros(),
+
+            mass: 1.0,
+            inertia_tensor: Matrix3::identity(),
+            local_center_of_mass: Vector3::zeros(),
+            locked_axes: LockedAxes::NONE,
+            gravity_scale: 1.0,
+            ccd_enabled: false,
+
+            damping: None,
+
+            body_type: BodyType::Dynamic,
+            sleeping: false,
+
+            user_data: 0
+        }
+    }
+}
+
+impl RigidBodyDesc {
+    pub fn new(body_type: BodyType) -> Self {
+        Self { body_type, ..Default::default() }
+    }
+
+    #[inline]
+    pub fn dynamic() -> Self {
+        Self::new(BodyType::Dynamic)
+    }
+
+    #[inline]
+    pub fn fixed() -> Self {
+        Self::new(BodyType::Static)
+    }
+
+    #[inline]
+    pub fn kinematic() -> Self {
+        Self::new(BodyType::Kinematic)
+    }
+
+    pub fn with_position(mut self, position: Point3<Precision>) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn with_orientation(mut self, orientation: UnitQuaternion<Precision>) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    pub fn with_linear_velocity(mut self, linear_velocity: Vector3<Precision>) -> Self {
+        self.linear_velocity = linear_velocity;
+        self
+    }
+
+    pub fn with_angular_velocity(mut self, angular_velocity: Vector3<Precision>) -> Self {
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    pub fn with_mass(mut self, mass: Precision, inertia_tensor: Matrix3<Precision>) -> Self {
+        self.mass = mass;
+        self.inertia_tensor = inertia_tensor;
+        self
+    }
+
+    pub fn with_center_of_mass(mut self, local_center_of_mass: Vector3<Precision>) -> Self {
+        self.local_center_of_mass = local_center_of_mass;
+        self
+    }
+
+    pub fn with_locked_axes(mut self, locked_axes: LockedAxes) -> Self {
+        self.locked_axes = locked_axes;
+        self
+    }
+
+    pub fn with_gravity_scale(mut self, gravity_scale: Precision) -> Self {
+        self.gravity_scale = gravity_scale;
+        self
+    }
+
+    pub fn with_ccd(mut self, ccd_enabled: bool) -> Self {
+        self.ccd_enabled = ccd_enabled;
+        self
+    }
+
+    pub fn with_damping(mut self, damping: Damping) -> Self {
+        self.damping = Some(damping);
+        self
+    }
+
+    pub fn sleeping(mut self, sleeping: bool) -> Self {
+        self.sleeping = sleeping;
+        self
+    }
+
+    pub fn with_user_data(mut self, user_data: u64) -> Self {
+        self.user_data = user_data;
+        self
+    }
+}
@@ -1,29 +1,80 @@
+use std::any::Any;
+
 use itertools::izip;
-use nalgebra::{Point3, UnitVector3};
-use crate::{BodyHandle, BodySet, Precision, EPSILON};
+use nalgebra::{Point3, UnitVector3, Vector3};
+use smallvec::SmallVec;
+use crate::{BodyDelta, BodyHandle, BodySet, Precision, EPSILON};
+
+// one entry per body, stored inline for the common two body case so solving does not allocate
+pub type BodyVec<T> = SmallVec<[T; 2]>;
+
+// the parallel solvers hand constraints to other threads, without the parallel feature they need not be Send or Sync
+#[cfg(feature = "parallel")]
+pub trait MaybeSendSync: Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync> MaybeSendSync for T {}
+
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSendSync {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> MaybeSendSync for T {}
+
+// Any lets the world hand constraints back as their concrete type
+pub trait Constraint: Any + MaybeSendSync {
+    fn bodies(&self) -> BodyVec<BodyHandle>;
 
-pub trait Constraint {
     fn compute_correction(&self, bodies: &BodySet) -> Option<CorrectionData>;
 
-    fn solve(&mut self, bodies: &mut BodySet, lambda: &mut Precision, dt: Precision) {
-        if let Some(correction) = self.compute_correction(bodies) {
-            correction.apply_correction(bodies, lambda, dt);
+    // returns the unit direction of the applied correction, used to turn lambda into a force,
+    // only the gauss seidel solver calls this, the coloured and jacobi solvers go through compute_correction
+    fn solve(&mut self, bodies: &mut BodySet, lambda: &mut Precision, dt: Precision) -> Option<ConstraintForce> {
+        let correction = self.compute_correction(bodies)?;
+
+        correction.apply_correction(bodies, lambda, dt);
+
+        Some(correction.direction())
+    }
+}
+
+// what the constraint exerts on its first body
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ConstraintForce {
+    Force(Vector3<Precision>),
+    Torque(Vector3<Precision>)
+}
+
+impl ConstraintForce {
+    #[inline]
+    pub fn magnitude(&self) -> Precision {
+        match self {
+            ConstraintForce::Force(force) => force.norm(),
+            ConstraintForce::Torque(torque) => torque.norm()
+        }
+    }
+
+    #[inline]
+    pub fn scaled(&self, factor: Precision) -> Self {
+        match self {
+            ConstraintForce::Force(force) => ConstraintForce::Force(force * factor),
+            ConstraintForce::Torque(torque) => ConstraintForce::Torque(torque * factor)
         }
     }
 }
 
 pub enum CorrectionData {
     Translational {
-        handles: Vec<BodyHandle>,
-        relative_points: Vec<Point3<Precision>>,
-        normals: Vec<UnitVector3<Precision>>,
+        handles: BodyVec<BodyHandle>,
+        relative_points: BodyVec<Point3<Precision>>,
+        normals: BodyVec<UnitVector3<Precision>>,
 
         error: Precision,
         alpha: Precision // compliance
     },
     Rotational {
-        handles: Vec<BodyHandle>,
-        axes: Vec<UnitVector3<Precision>>,
+        handles: BodyVec<BodyHandle>,
+        axes: BodyVec<UnitVector3<Precision>>,
 
         error: Precision,
         alpha: Precision // compliance
@@ -31,104 +82,125 @@ pub enum CorrectionData {
 }
 
 impl CorrectionData {
-    pub fn apply_correction(&self, bodies: &mut BodySet, lambda: &mut Precision, dt: Precision) {
+    // correction on the first body per unit lambda
+    pub fn direction(&self) -> ConstraintForce {
         match self {
-            CorrectionData::Translational { handles, relative_points, normals, error, alpha } =>
-                Self::apply_translational_correction(bodies, lambda, handles, relative_points, normals, *error, *alpha, dt),
-            CorrectionData::Rotational { handles, axes, error, alpha } =>
-                Self::apply_rotational_correction(bodies, lambda, handles, axes, *error, *alpha, dt)
+            CorrectionData::Translational { normals, .. } =>
+                ConstraintForce::Force(normals.first().map_or(Vector3::zeros(), |normal| normal.into_inner())),
+            CorrectionData::Rotational { axes, .. } =>
+                ConstraintForce::Torque(axes.first().map_or(Vector3::zeros(), |axis| axis.into_inner()))
         }
     }
 
-    fn apply_translational_correction(
-        bodies: &mut BodySet,
-        lambda: &mut Precision,
+    pub fn apply_correction(&self, bodies: &mut BodySet, lambda: &mut Precision, dt: Precision) {
+        for delta in self.compute_deltas(bodies, lambda, dt) {
+            bodies.apply_delta(&delta);
+        }
+    }
 
-        handles: &[BodyHandle],
-        relative_points: &[Point3<Precision>],
-        normals: &[UnitVector3<Precision>],
+    // updates lambda and returns what the correction does to each body without touching them
+    #[inline]
+    pub fn compute_deltas(&self, bodies: &BodySet, lambda: &mut Precision, dt: Precision) -> BodyVec<BodyDelta> {
+        self.compute_split_deltas(bodies, lambda, dt, |_| 1.0, |_| 1.0)
+    }
 
-        error: Precision,
-        alpha: Precision,
+    // as if each body only had 1 / mass_split(body) of its mass, for solvers that share bodies between constraints,
+    // applied(body) is the share of its delta the solver ends up giving the body, lambda only grows by what was applied
+    pub fn compute_split_deltas(
+        &self,
+        bodies: &BodySet,
+        lambda: &mut Precision,
+        dt: Precision,
+        mass_split: impl Fn(usize) -> Precision,
+        applied: impl Fn(usize) -> Precision
+    ) -> BodyVec<BodyDelta> {
+        let (error, alpha) = match self {
+            CorrectionData::Translational { error, alpha, .. } => (*error, *alpha),
+            CorrectionData::Rotational { error, alpha, .. } => (*error, *alpha)
+        };
 
-        dt: Precision
-    ) {
         let alpha_tilde = if alpha > 0.0 {
             alpha / (dt * dt)
         } else {
             0.0
         };
-        let mut total_inverse_mass = alpha_tilde;
 
-        for (handle, relative_point, normal) in izip!(handles, relative_points, normals) {
-            let body = **handle;
+        let gradients = self.gradients();
+        let inverse_masses: BodyVec<Precision> = gradients.iter()
+            .map(|gradient| mass_split(gradient.body) * gradient.inverse_mass(bodies))
+            .collect();
 
-            let perpendicular = relative_point.coords.cross(normal);
+        let body_inverse_mass = inverse_masses.iter().sum::<Precision>();
+        let total_inverse_mass = alpha_tilde + body_inverse_mass;
 
-            total_inverse_mass += bodies.inverse_mass[body] + (bodies.inverse_inertia_tensor_world[body] * perpendicular).dot(&perpendicular);
-        }
-
-        if total_inverse_mass < EPSILON { return; }
+        if total_inverse_mass < EPSILON { return BodyVec::new(); }
 
         let d_lambda = -(error + *lambda * alpha_tilde) / total_inverse_mass;
 
-        *lambda += d_lambda;
-
-        for (handle, relative_point, normal) in izip!(handles, relative_points, normals) {
-            let body = **handle;
-
-            if !bodies.has_finite_mass(body) { continue; }
-
-            let correction_impulse = normal.into_inner() * d_lambda;
-            let rotational_correction = relative_point.coords.cross(&correction_impulse);
-
-            bodies.position[body] += bodies.inverse_mass[body] * correction_impulse;
-
-            bodies.apply_rotation_delta(body, bodies.inverse_inertia_tensor_world[body] * rotational_correction);
-            bodies.update_derived_data(body);
-        }
-    }
-
-    fn apply_rotational_correction(
-        bodies: &mut BodySet,
-        lambda: &mut Precision,
-
-        handles: &[BodyHandle],
-        axes: &[UnitVector3<Precision>],
-
-        error: Precision,
-        alpha: Precision,
-
-        dt: Precision
-    ) {
-        let alpha_tilde = if alpha > 0.0 {
-            alpha / (dt * dt)
+        // each body moves by its applied share of the correction, weighted by how much of it the body takes
+        let applied_share = if body_inverse_mass > 0.0 {
+            izip!(&gradients, &inverse_masses)
+                .map(|(gradient, inverse_mass)| inverse_mass * applied(gradient.body))
+                .sum::<Precision>() / body_inverse_mass
         } else {
-            0.0
+            1.0
         };
-        let mut total_inverse_mass = alpha_tilde;
 
-        for (handle, axis) in izip!(handles, axes) {
-            let body = **handle;
+        *lambda += d_lambda * applied_share;
 
-            total_inverse_mass += (bodies.inverse_inertia_tensor_world[body] * axis.into_inner()).dot(&axis);
-        }
+        gradients.iter()
+            .filter(|gradient| bodies.has_finite_mass(gradient.body))
+            .map(|gradient| {
+                let body = gradient.body;
+                let scale = d_lambda * mass_split(body);
 
-        if total_inverse_mass < EPSILON { return; }
+                BodyDelta {
+                    body,
 
-        let d_lambda = -(error + *lambda * alpha_tilde) / total_inverse_mass;
-
-        *lambda += d_lambda;
+                    translation: bodies.effective_inverse_mass(body).component_mul(&(gradient.linear * scale)),
+                    rotation: bodies.effective_inverse_inertia_tensor(body) * (gradient.angular * scale)
+                }
+            })
+            .collect()
+    }
 
-        for (handle, axis) in izip!(handles, axes) {
-            let body = **handle;
+    // both kinds of correction as a linear and an angular direction per body
+    fn gradients(&self) -> BodyVec<Gradient> {
+        match self {
+            CorrectionData::Translational { handles, relative_points, normals, .. } =>
+                izip!(handles, relative_points, normals)
+                    .map(|(handle, relative_point, normal)| Gradient {
+                        body: **handle,
+
+                        linear: normal.into_inner(),
+                        angular: relative_point.coords.cross(normal)
+                    })
+                    .collect(),
+            CorrectionData::Rotational { handles, axes, .. } =>
+                izip!(handles, axes)
+                    .map(|(handle, axis)| Gradient {
+                        body: **handle,
+
+                        linear: Vector3::zeros(),
+                        angular: axis.into_inner()
+                    })
+                    .collect()
+        }
+    }
+}
 
-            if !bodies.has_finite_mass(body) { continue; }
+struct Gradient {
+    body: usize,
 
-            let correction_impulse = axis.into_inner() * d_lambda;
+    linear: Vector3<Precision>,
+    angular: Vector3<Precision>
+}
 
-            bodies.apply_rotation_delta(body, bodies.inverse_inertia_tensor_world[body] * correction_impulse);
-            bodies.update_derived_data(body);
-        }
+impl Gradient {
+    // how far the body gives way per unit lambda
+    #[inline]
+    fn inverse_mass(&self, bodies: &BodySet) -> Precision {
+        bodies.effective_inverse_mass(self.body).component_mul(&self.linear).dot(&self.linear) +
+            (bodies.effective_inverse_inertia_tensor(self.body) * self.angular).dot(&self.angular)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,205 @@
+use std::any::Any;
+use std::ops::Deref;
+
+use crate::{Constraint, ConstraintForce, Precision};
+
+// index into the constraint set plus the generation of the slot, like body handles
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ConstraintHandle {
+    index: usize,
+    generation: u32
+}
+
+impl ConstraintHandle {
+    pub const INVALID: Self = Self { index: usize::MAX, generation: u32::MAX };
+
+    pub fn new(index: usize, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl Deref for ConstraintHandle {
+    type Target = usize;
+
+    fn deref(&self) -> &Self::Target {
+        &self.index
+    }
+}
+
+// a constraint breaks once the force or torque it exerts goes past these
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BreakThreshold {
+    pub max_force: Precision,
+    pub max_torque: Precision
+}
+
+impl Default for BreakThreshold {
+    fn default() -> Self {
+        Self {
+            max_force: Precision::INFINITY,
+            max_torque: Precision::INFINITY
+        }
+    }
+}
+
+impl BreakThreshold {
+    pub fn new(max_force: Precision, max_torque: Precision) -> Self {
+        Self { max_force, max_torque }
+    }
+
+    #[inline]
+    pub fn is_exceeded_by(&self, force: &ConstraintForce) -> bool {
+        match force {
+            ConstraintForce::Force(_) => force.magnitude() > self.max_force,
+            ConstraintForce::Torque(_) => force.magnitude() > self.max_torque
+        }
+    }
+}
+
+// per constraint state by slot, only changed through the world so removals wake the bodies and keep the free list right
+#[derive(Default)]
+pub struct ConstraintSet {
+    pub(crate) constraints: Vec<Option<Box<dyn Constraint>>>, // None while a removed slot waits for reuse
+    pub(crate) enabled: Vec<bool>,
+    pub(crate) break_threshold: Vec<Option<BreakThreshold>>, // None for unbreakable constraints
+    pub(crate) user_data: Vec<u64>, // opaque to the engine
+
+    // solver output of the last sub step
+    pub(crate) lambda: Vec<Precision>,
+    pub(crate) direction: Vec<Option<ConstraintForce>>, // per unit lambda, None when nothing was corrected
+
+    // slot bookkeeping
+    generations: Vec<u32>,
+    free_list: Vec<usize>
+}
+
+impl ConstraintSet {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn insert(&mut self, constraint: Box<dyn Constraint>) -> ConstraintHandle {
+        if let Some(i) = self.free_list.pop() {
+            self.constraints[i] = Some(constraint);
+            self.enabled[i] = true;
+            self.break_threshold[i] = None;
+            self.user_data[i] = 0;
+
+            self.lambda[i] = 0.0;
+            self.direction[i] = None;
+
+            return ConstraintHandle::new(i, self.generations[i]);
+        }
+
+        self.constraints.push(Some(constraint));
+        self.enabled.push(true);
+        self.break_threshold.push(None);
+        self.user_data.push(0);
+
+        self.lambda.push(0.0);
+        self.direction.push(None);
+
+        self.generations.push(0);
+
+        ConstraintHandle::new(self.constraints.len() - 1, 0)
+    }
+
+    pub(crate) fn remove(&mut self, handle: ConstraintHandle) -> Option<Box<dyn Constraint>> {
+        if !self.contains(handle) { return None; }
+
+        let i = *handle;
+
+        self.enabled[i] = false;
+
+        self.lambda[i] = 0.0;
+        self.direction[i] = None;
+
+        self.generations[i] = self.generations[i].wrapping_add(1);
+        self.free_list.push(i);
+
+        self.constraints[i].take()
+    }
+
+    #[inline]
+    pub fn contains(&self, handle: ConstraintHandle) -> bool {
+        self.generations.get(*handle).is_some_and(|&g| g == handle.generation) && self.constraints[*handle].is_some()
+    }
+
+    #[inline]
+    pub fn is_alive(&self, i: usize) -> bool {
+        self.constraints[i].is_some()
+    }
+
+    // alive and taking part in the solve
+    #[inline]
+    pub fn is_enabled(&self, i: usize) -> bool {
+        self.enabled[i]
+    }
+
+    // number of live constraints, slots are counted by constraints.len()
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.constraints.len() - self.free_list.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn handle(&self, i: usize) -> ConstraintHandle {
+        ConstraintHandle::new(i, self.generations[i])
+    }
+
+    pub fn get(&self, handle: ConstraintHandle) -> Option<&(dyn Constraint + 'static)> {
+        if !self.contains(handle) { return None; }
+
+        self.constraints[*handle].as_deref()
+    }
+
+    pub(crate) fn get_mut(&mut self, handle: ConstraintHandle) -> Option<&mut (dyn Constraint + 'static)> {
+        if !self.contains(handle) { return None; }
+
+        self.constraints[*handle].as_deref_mut()
+    }
+
+    // None when the handle is stale or the constraint is of another type
+    pub fn downcast_ref<T: Constraint>(&self, handle: ConstraintHandle) -> Option<&T> {
+        (self.get(handle)? as &dyn Any).downcast_ref()
+    }
+
+    pub(crate) fn downcast_mut<T: Constraint>(&mut self, handle: ConstraintHandle) -> Option<&mut T> {
+        (self.get_mut(handle)? as &mut dyn Any).downcast_mut()
+    }
+
+    // lambda over the squared sub step turns the positional correction back into a force on the first body
+    // None when the constraint corrected nothing during the last sub step
+    pub fn force(&self, handle: ConstraintHandle, sub_dt: Precision) -> Option<ConstraintForce> {
+        if !self.contains(handle) || sub_dt <= 0.0 { return None; }
+
+        let i = *handle;
+
+        Some(self.direction[i]?.scaled(self.lambda[i] / (sub_dt * sub_dt)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ConstraintHandle, &dyn Constraint)> {
+        self.constraints.iter()
+            .enumerate()
+            .filter_map(|(i, constraint)| Some((self.handle(i), constraint.as_deref()?)))
+    }
+
+    // live constraints that take part in the solve
+    pub fn iter_enabled(&self) -> impl Iterator<Item = (usize, &dyn Constraint)> {
+        self.constraints.iter()
+            .enumerate()
+            .filter(|(i, _)| self.enabled[*i])
+            .filter_map(|(i, constraint)| Some((i, constraint.as_deref()?)))
+    }
+}
@@ -0,0 +1,12 @@
+use crate::{ConstraintForce, ConstraintHandle};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    // the constraint went past its break threshold and was disabled, it can be re-enabled or removed
+    JointBroken {
+        constraint: ConstraintHandle,
+        force: ConstraintForce,
+
+        user_data: u64
+    }
+}
@@ -0,0 +1,164 @@
+use nalgebra::{Isometry3, Point3, Translation3, UnitQuaternion};
+use crate::{math, BodyHandle, Precision, World};
+
+// steps the world in fixed increments of real time so results do not depend on the frame rate,
+// rendering blends between the last two steps using the leftover time
+pub struct FixedTimestep {
+    dt: Precision, // always positive, a zero step would never use up the accumulated time
+    pub max_steps: usize, // per advance, slow frames drop time instead of falling further behind
+
+    accumulator: Precision,
+
+    // poses before the last step
+    previous_handles: Vec<BodyHandle>,
+    previous_position: Vec<Point3<Precision>>,
+    previous_orientation: Vec<UnitQuaternion<Precision>>
+}
+
+impl FixedTimestep {
+    pub fn new(dt: Precision, max_steps: usize) -> Self {
+        assert!(dt > 0.0, "the fixed timestep has to be positive, got {dt}");
+
+        Self {
+            dt, max_steps,
+
+            accumulator: 0.0,
+
+            previous_handles: Vec::new(),
+            previous_position: Vec::new(),
+            previous_orientation: Vec::new()
+        }
+    }
+
+    #[inline]
+    pub fn dt(&self) -> Precision {
+        self.dt
+    }
+
+    pub fn set_dt(&mut self, dt: Precision) {
+        assert!(dt > 0.0, "the fixed timestep has to be positive, got {dt}");
+
+        self.dt = dt;
+    }
+
+    // returns the number of steps taken
+    pub fn advance(&mut self, world: &mut World, elapsed: Precision) -> usize {
+        self.accumulator += elapsed.max(0.0);
+
+        let mut steps = 0;
+
+        while self.accumulator >= self.dt && steps < self.max_steps {
+            self.save_poses(world);
+
+            world.step(self.dt);
+
+            self.accumulator -= self.dt;
+            steps += 1;
+        }
+
+        // keep only the fraction of a step, the rest could never be caught up on
+        if self.accumulator >= self.dt {
+            self.accumulator %= self.dt;
+        }
+
+        steps
+    }
+
+    // how far the current time is between the last step and the next one
+    #[inline]
+    pub fn alpha(&self) -> Precision {
+        (self.accumulator / self.dt).clamp(0.0, 1.0)
+    }
+
+    #[inline]
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+
+        self.previous_handles.clear();
+        self.previous_position.clear();
+        self.previous_orientation.clear();
+    }
+
+    // None for stale handles, bodies created since the last step are shown where they are
+    pub fn interpolated_pose(&self, world: &World, handle: BodyHandle) -> Option<Isometry3<Precision>> {
+        if !world.bodies.contains(handle) { return None; }
+
+        let i = *handle;
+        let position = world.bodies.position[i];
+        let orientation = world.bodies.orientation[i];
+
+        if self.previous_handles.get(i) != Some(&handle) {
+            return Some(Isometry3::from_parts(Translation3::from(position), orientation));
+        }
+
+        let alpha = self.alpha();
+
+        let position = self.previous_position[i].coords.lerp(&position.coords, alpha);
+        let orientation = math::quaternion_slerp(&self.previous_orientation[i], &orientation, alpha);
+
+        Some(Isometry3::from_parts(Translation3::from(position), orientation))
+    }
+
+    pub fn interpolated_poses<'a>(&'a self, world: &'a World) -> impl Iterator<Item = (BodyHandle, Isometry3<Precision>)> + 'a {
+        world.bodies.handles().filter_map(|handle| Some((handle, self.interpolated_pose(world, handle)?)))
+    }
+
+    fn save_poses(&mut self, world: &World) {
+        let bodies = &world.bodies;
+
+        self.previous_handles.clear();
+        self.previous_handles.extend((0..bodies.position.len()).map(|i| {
+            if bodies.is_alive(i) { bodies.handle(i) } else { BodyHandle::INVALID }
+        }));
+
+        self.previous_position.clone_from(&bodies.position);
+        self.previous_orientation.clone_from(&bodies.orientation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::Vector3;
+    use crate::RigidBodyDesc;
+    use super::*;
+
+    #[test]
+    fn whole_steps_are_taken_up_to_the_cap() {
+        let mut world = World::new(Vector3::zeros(), 1, 1);
+        let mut timestep = FixedTimestep::new(0.25, 4);
+
+        assert_eq!(timestep.advance(&mut world, 0.9), 3);
+        assert_eq!(timestep.advance(&mut world, 0.05), 0);
+        assert_eq!(timestep.advance(&mut world, 0.1), 1);
+
+        // the time past the cap is dropped, only the fraction of a step is kept
+        assert_eq!(timestep.advance(&mut world, 1.6), 4);
+        assert!((timestep.alpha() - 0.6).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn poses_are_blended_from_the_last_step_to_the_current_one() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 1, 1);
+        let mut timestep = FixedTimestep::new(0.25, 4);
+
+        let body = world.create_body(RigidBodyDesc::dynamic());
+
+        let before = world.bodies.pose(body).unwrap();
+
+        assert_eq!(timestep.advance(&mut world, 0.25), 1);
+        assert_eq!(timestep.alpha(), 0.0);
+        assert_eq!(timestep.interpolated_pose(&world, body), Some(before));
+
+        timestep.accumulator = timestep.dt();
+
+        assert_eq!(timestep.alpha(), 1.0);
+        assert_eq!(timestep.interpolated_pose(&world, body), world.bodies.pose(body));
+        assert_ne!(world.bodies.pose(body), Some(before));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_timesteps_are_rejected() {
+        FixedTimestep::new(1.0 / 60.0, 4).set_dt(0.0);
+    }
+}
@@ -0,0 +1,148 @@
+use nalgebra::{Point3, Vector3};
+use crate::{MaybeSendSync, Precision, EPSILON_SQUARED};
+
+pub trait GravityField: MaybeSendSync {
+    fn acceleration_at(&self, point: &Point3<Precision>) -> Vector3<Precision>;
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct UniformGravity(pub Vector3<Precision>);
+
+impl GravityField for UniformGravity {
+    #[inline]
+    fn acceleration_at(&self, _: &Point3<Precision>) -> Vector3<Precision> {
+        self.0
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct GravityWell {
+    pub center: Point3<Precision>,
+    pub strength: Precision, // G * M, acceleration at unit distance
+
+    pub min_distance: Precision // keeps the pull finite near the centre
+}
+
+// inverse square attraction towards any number of centres
+#[derive(Clone, Debug, Default)]
+pub struct PointGravity {
+    pub wells: Vec<GravityWell>
+}
+
+impl PointGravity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_well(mut self, center: Point3<Precision>, strength: Precision) -> Self {
+        self.wells.push(GravityWell { center, strength, min_distance: 0.0 });
+        self
+    }
+}
+
+impl GravityField for PointGravity {
+    fn acceleration_at(&self, point: &Point3<Precision>) -> Vector3<Precision> {
+        self.wells.iter()
+            .map(|well| {
+                let offset = well.center - point;
+                let distance_sq = offset.norm_squared().max(well.min_distance * well.min_distance);
+
+                if distance_sq < EPSILON_SQUARED { return Vector3::zeros(); }
+
+                offset * (well.strength / (distance_sq * distance_sq.sqrt()))
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{RigidBodyDesc, World};
+    use super::*;
+
+    fn close(a: Vector3<Precision>, b: Vector3<Precision>) -> bool {
+        (a - b).norm() < 1e-5
+    }
+
+    #[test]
+    fn wells_pull_towards_their_centre() {
+        let field = PointGravity::new().with_well(Point3::new(1.0, 2.0, 3.0), 8.0);
+
+        assert!(close(field.acceleration_at(&Point3::new(1.0, 0.0, 3.0)), Vector3::new(0.0, 2.0, 0.0)));
+        assert!(close(field.acceleration_at(&Point3::new(-1.0, 2.0, 3.0)), Vector3::new(2.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn wells_fall_off_with_the_inverse_square() {
+        let field = PointGravity::new().with_well(Point3::origin(), 1.0);
+
+        let near = field.acceleration_at(&Point3::new(0.0, 0.0, 1.0)).norm();
+        let far = field.acceleration_at(&Point3::new(0.0, 0.0, 3.0)).norm();
+
+        assert!((near / far - 9.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn wells_stay_finite_at_their_centre() {
+        let field = PointGravity::new().with_well(Point3::origin(), 1.0);
+
+        assert_eq!(field.acceleration_at(&Point3::origin()), Vector3::zeros());
+
+        let mut field = field;
+        field.wells[0].min_distance = 0.5;
+
+        // inside the minimum distance the pull never exceeds its value at that distance
+        let inside = field.acceleration_at(&Point3::new(0.0, 1e-3, 0.0));
+
+        assert!(inside.norm() <= 4.0 && inside.y < 0.0);
+    }
+
+    #[test]
+    fn summed_wells_cancel_between_them() {
+        let field = PointGravity::new()
+            .with_well(Point3::new(-1.0, 0.0, 0.0), 2.0)
+            .with_well(Point3::new(1.0, 0.0, 0.0), 2.0);
+
+        assert!(close(field.acceleration_at(&Point3::origin()), Vector3::zeros()));
+    }
+
+    #[test]
+    fn zero_gravity_scale_ignores_the_field() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 4, 1);
+
+        let floating = world.create_body(RigidBodyDesc::dynamic().with_gravity_scale(0.0));
+        let falling = world.create_body(RigidBodyDesc::dynamic());
+
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+        }
+
+        assert_eq!(world.bodies().position(floating), Some(Point3::origin()));
+        assert!(world.bodies().position(falling).unwrap().y < -4.0);
+    }
+
+    #[test]
+    fn point_gravity_moves_bodies_towards_the_well() {
+        let mut world = World::new(Vector3::zeros(), 4, 1);
+
+        world.set_gravity_field(PointGravity::new().with_well(Point3::new(0.0, 10.0, 0.0), 100.0));
+
+        let body = world.create_body(RigidBodyDesc::dynamic());
+
+        for _ in 0..10 {
+            world.step(1.0 / 60.0);
+        }
+
+        let position = world.bodies().position(body).unwrap();
+
+        assert!(position.y > 0.0 && position.x == 0.0 && position.z == 0.0);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn worlds_with_custom_fields_are_send() {
+        fn assert_send<T: Send>() {}
+
+        assert_send::<World>();
+    }
+}
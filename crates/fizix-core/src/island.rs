@@ -0,0 +1,242 @@
+use crate::{BodySet, ConstraintSet, Precision};
+
+#[derive(Copy, Clone, Debug)]
+pub struct SleepSettings {
+    pub enabled: bool, // off by default, resting bodies stop moving entirely once asleep
+
+    // a body is at rest while both speeds stay under these
+    pub linear_threshold: Precision,
+    pub angular_threshold: Precision,
+
+    pub time_to_sleep: Precision // every body of an island must rest this long
+}
+
+impl Default for SleepSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+
+            linear_threshold: 0.05,
+            angular_threshold: 0.05,
+
+            time_to_sleep: 0.5
+        }
+    }
+}
+
+// groups of dynamic bodies connected through constraints, static and kinematic bodies never join an island
+// stored flat so that rebuilding them every step reuses the same buffers
+#[derive(Default)]
+pub(crate) struct Islands {
+    parent: Vec<usize>,
+    island_of_root: Vec<usize>,
+    island_of_body: Vec<usize>,
+
+    bodies: Vec<usize>, // grouped by island, ascending within each
+    offsets: Vec<usize> // start of each island in bodies, followed by the end of the last
+}
+
+impl Islands {
+    pub(crate) fn build(&mut self, bodies: &BodySet, constraints: &ConstraintSet) {
+        let count = bodies.position.len();
+        let parent = &mut self.parent;
+
+        parent.clear();
+        parent.extend(0..count);
+
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+
+            i
+        }
+
+        // disabled constraints no longer tie their bodies together
+        for (_, constraint) in constraints.iter_enabled() {
+            let mut root: Option<usize> = None;
+
+            for body in constraint.bodies() {
+                let i = *body;
+
+                if !bodies.is_alive(i) || !bodies.has_finite_mass(i) { continue; }
+
+                let i = find(parent, i);
+
+                match root {
+                    // smaller index as root keeps island order stable
+                    Some(r) if r != i => {
+                        let (low, high) = if r < i { (r, i) } else { (i, r) };
+
+                        parent[high] = low;
+                        root = Some(low);
+                    },
+                    Some(_) => {},
+                    None => root = Some(i)
+                }
+            }
+        }
+
+        self.island_of_root.clear();
+        self.island_of_root.resize(count, usize::MAX);
+        self.island_of_body.clear();
+        self.island_of_body.resize(count, usize::MAX);
+
+        // count the bodies of each island, islands are numbered by their lowest body
+        self.offsets.clear();
+
+        for i in 0..count {
+            if !bodies.is_alive(i) || !bodies.has_finite_mass(i) { continue; }
+
+            let root = find(parent, i);
+
+            if self.island_of_root[root] == usize::MAX {
+                self.island_of_root[root] = self.offsets.len();
+                self.offsets.push(0);
+            }
+
+            self.island_of_body[i] = self.island_of_root[root];
+            self.offsets[self.island_of_root[root]] += 1;
+        }
+
+        let mut total = 0;
+
+        for offset in &mut self.offsets {
+            total += *offset;
+            *offset = total - *offset;
+        }
+
+        self.offsets.push(total);
+
+        // counting sort, the root buffer is free again and serves as the write cursor
+        self.island_of_root.clear();
+        self.island_of_root.extend_from_slice(&self.offsets);
+
+        self.bodies.clear();
+        self.bodies.resize(total, 0);
+
+        for (i, &island) in self.island_of_body.iter().enumerate() {
+            if island == usize::MAX { continue; }
+
+            self.bodies[self.island_of_root[island]] = i;
+            self.island_of_root[island] += 1;
+        }
+    }
+
+    #[inline]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &[usize]> {
+        self.offsets.windows(2).map(|range| &self.bodies[range[0]..range[1]])
+    }
+
+    #[inline]
+    pub(crate) fn bodies(&self) -> &[usize] {
+        &self.bodies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{Point3, Vector3};
+    use crate::testing::Rope;
+    use crate::{BodyHandle, RigidBodyDesc, World};
+    use super::*;
+
+    // a body hanging at rest below an anchor of the given kind
+    fn hanging(anchor: RigidBodyDesc) -> (World, BodyHandle, BodyHandle) {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 8, 2);
+
+        world.sleep_settings.enabled = true;
+
+        let anchor = world.create_body(anchor);
+        let body = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(0.0, -1.0, 0.0)));
+
+        world.add_constraint(Rope::new(anchor, body, 1.0)).unwrap();
+
+        (world, anchor, body)
+    }
+
+    fn step_for(world: &mut World, seconds: Precision) {
+        for _ in 0..(seconds * 60.0) as usize {
+            world.step(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn sleeping_is_opt_in() {
+        let (mut world, _, body) = hanging(RigidBodyDesc::fixed());
+
+        world.sleep_settings = SleepSettings::default();
+
+        step_for(&mut world, 2.0);
+
+        assert!(!world.is_body_sleeping(body));
+    }
+
+    #[test]
+    fn resting_bodies_fall_asleep() {
+        let (mut world, _, body) = hanging(RigidBodyDesc::fixed());
+
+        step_for(&mut world, 0.25);
+
+        assert!(!world.is_body_sleeping(body));
+
+        step_for(&mut world, 1.0);
+
+        assert!(world.is_body_sleeping(body));
+        assert_eq!(world.bodies.linear_velocity(body), Some(Vector3::zeros()));
+    }
+
+    #[test]
+    fn impulses_and_forces_wake_bodies() {
+        let pushes: [fn(&mut World, BodyHandle) -> bool; 3] = [
+            |world, body| world.apply_impulse(body, Vector3::x()),
+            |world, body| world.apply_force(body, Vector3::x()),
+            |world, body| world.wake_body(body)
+        ];
+
+        for push in pushes {
+            let (mut world, _, body) = hanging(RigidBodyDesc::fixed());
+
+            step_for(&mut world, 1.0);
+            assert!(world.is_body_sleeping(body));
+
+            assert!(push(&mut world, body));
+            assert!(!world.is_body_sleeping(body));
+
+            world.step(1.0 / 60.0);
+            assert!(!world.is_body_sleeping(body));
+        }
+    }
+
+    #[test]
+    fn new_constraints_wake_their_bodies() {
+        let (mut world, _, body) = hanging(RigidBodyDesc::fixed());
+        let other = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(0.0, -2.0, 0.0)));
+
+        step_for(&mut world, 1.0);
+        assert!(world.is_body_sleeping(body));
+
+        world.add_constraint(Rope::new(body, other, 1.0)).unwrap();
+        assert!(!world.is_body_sleeping(body));
+    }
+
+    #[test]
+    fn moving_kinematic_bodies_wake_their_island() {
+        let (mut world, anchor, body) = hanging(RigidBodyDesc::kinematic());
+        let below = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(0.0, -2.0, 0.0)));
+
+        world.add_constraint(Rope::new(body, below, 1.0)).unwrap();
+
+        step_for(&mut world, 1.0);
+        assert!(world.is_body_sleeping(body));
+        assert!(world.is_body_sleeping(below));
+
+        world.set_kinematic_velocity(anchor, Vector3::x(), Vector3::zeros());
+        world.step(1.0 / 60.0);
+
+        assert!(!world.is_body_sleeping(body));
+        assert!(!world.is_body_sleeping(below));
+        assert!(world.bodies.position(below).unwrap().x > 0.0);
+    }
+}
@@ -1,12 +1,46 @@
+pub mod math;
+
 mod world;
 mod body;
+mod body_desc;
 mod constraint;
+mod constraint_set;
+mod event;
+mod fixed_timestep;
+mod gravity;
+mod island;
+mod solver;
+
+#[cfg(test)]
+mod testing;
 
+// f64 unless the f32 feature is enabled, the epsilon follows the precision
+#[cfg(not(feature = "f32"))]
 pub type Precision = f64;
+#[cfg(feature = "f32")]
+pub type Precision = f32;
 
+#[cfg(not(feature = "f32"))]
 pub const EPSILON: Precision = 1e-9;
+#[cfg(feature = "f32")]
+pub const EPSILON: Precision = 1e-5;
+
 pub const EPSILON_SQUARED: Precision = EPSILON * EPSILON;
 
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+
 pub use world::*;
 pub use body::*;
-pub use constraint::*;
\ No newline at end of file
+pub use body_desc::*;
+pub use constraint::*;
+pub use constraint_set::*;
+pub use event::*;
+pub use fixed_timestep::*;
+pub use gravity::*;
+pub use island::*;
+pub use solver::*;
+
+pub use smallvec::smallvec;
\ No newline at end of file
@@ -0,0 +1,69 @@
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
+use crate::{Precision, EPSILON};
+
+// transcendental functions the simulation depends on, with the deterministic feature they come from libm
+// so that results are bit identical across platforms and standard library versions
+
+#[cfg(all(feature = "deterministic", not(feature = "f32")))]
+pub use libm::{atan2, cos, exp, sin};
+
+#[cfg(all(feature = "deterministic", feature = "f32"))]
+pub use libm::{atan2f as atan2, cosf as cos, expf as exp, sinf as sin};
+
+#[cfg(not(feature = "deterministic"))]
+#[inline]
+pub fn atan2(y: Precision, x: Precision) -> Precision {
+    y.atan2(x)
+}
+
+#[cfg(not(feature = "deterministic"))]
+#[inline]
+pub fn cos(x: Precision) -> Precision {
+    x.cos()
+}
+
+#[cfg(not(feature = "deterministic"))]
+#[inline]
+pub fn exp(x: Precision) -> Precision {
+    x.exp()
+}
+
+#[cfg(not(feature = "deterministic"))]
+#[inline]
+pub fn sin(x: Precision) -> Precision {
+    x.sin()
+}
+
+// same as UnitQuaternion::from_scaled_axis but through the functions above
+pub fn quaternion_from_scaled_axis(scaled_axis: &Vector3<Precision>) -> UnitQuaternion<Precision> {
+    let angle = scaled_axis.norm();
+
+    if angle < EPSILON {
+        return UnitQuaternion::new_normalize(Quaternion::from_parts(1.0, scaled_axis * 0.5));
+    }
+
+    let half_angle = angle * 0.5;
+
+    UnitQuaternion::new_unchecked(Quaternion::from_parts(cos(half_angle), scaled_axis * (sin(half_angle) / angle)))
+}
+
+// rotation as a scaled axis, taking the short way round
+pub fn quaternion_scaled_axis(rotation: &UnitQuaternion<Precision>) -> Vector3<Precision> {
+    let rotation = if rotation.w < 0.0 { -rotation.into_inner() } else { rotation.into_inner() };
+    let sin_half_angle = rotation.imag().norm();
+
+    if sin_half_angle < EPSILON { return rotation.imag() * 2.0; }
+
+    rotation.imag() * (2.0 * atan2(sin_half_angle, rotation.w) / sin_half_angle)
+}
+
+// interpolates along the shortest arc, t = 0 gives from and t = 1 gives to
+pub fn quaternion_slerp(
+    from: &UnitQuaternion<Precision>,
+    to: &UnitQuaternion<Precision>,
+    t: Precision
+) -> UnitQuaternion<Precision> {
+    let delta = quaternion_scaled_axis(&(to * from.conjugate()));
+
+    quaternion_from_scaled_axis(&(delta * t)) * from
+}
@@ -0,0 +1,343 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use nalgebra::Vector3;
+use crate::{BodyDelta, BodySet, BodyVec, ConstraintForce, ConstraintSet, Precision};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SolverMode {
+    #[default]
+    GaussSeidel, // one constraint after another in slot order
+
+    // constraints are split into colours that share no dynamic body, each colour is solved at once
+    // and in parallel with the parallel feature, the result only depends on the colouring,
+    // overrides of Constraint::solve are skipped since the corrections are applied by the solver
+    Coloured,
+
+    // every constraint is solved against the same state and the corrections are combined per body,
+    // independent of constraint order and parallel with the parallel feature, skips Constraint::solve too
+    Jacobi(JacobiAveraging)
+}
+
+// how the corrections a body gets from several constraints in one jacobi iteration are combined
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum JacobiAveraging {
+    // mean of the corrections, lambda only grows by the share that was applied, the two ends of a constraint
+    // can get different shares though, so its force is only accurate to about half the load on one body
+    #[default]
+    ConstraintCount,
+
+    // each constraint sees the body with its mass divided between the constraints acting on it,
+    // so the combined correction no longer undershoots on heavily shared bodies
+    MassSplitting
+}
+
+// result of solving one constraint against a snapshot of the bodies
+type SolveResult = (Precision, Option<(ConstraintForce, BodyVec<BodyDelta>)>);
+
+// colours above this share one overflow group that is solved in order
+const MAX_COLOURS: usize = 64;
+
+// smaller batches cost more to hand to another thread than to solve
+#[cfg(feature = "parallel")]
+const MIN_PARALLEL_BATCH: usize = 256;
+
+#[derive(Default)]
+pub(crate) struct ColouredSolver {
+    colours: Vec<Vec<usize>>,
+    overflow: Vec<usize>,
+
+    body_colours: Vec<u64>, // bit set of the colours already touching each body
+    results: Vec<SolveResult>
+}
+
+impl ColouredSolver {
+    // greedy colouring in slot order, so the same constraints always end up with the same colours
+    pub(crate) fn colour(&mut self, constraints: &ConstraintSet, bodies: &BodySet, active: &[bool]) {
+        self.colours.iter_mut().for_each(Vec::clear);
+        self.overflow.clear();
+
+        self.body_colours.clear();
+        self.body_colours.resize(bodies.position.len(), 0);
+
+        for (i, constraint) in constraints.iter_enabled() {
+            if !active[i] { continue; }
+
+            // static and kinematic bodies are only read, so any number of colours may share them
+            let dynamic_bodies = || constraint.bodies()
+                .into_iter()
+                .map(|body| *body)
+                .filter(|&body| bodies.has_finite_mass(body));
+
+            let used = dynamic_bodies().fold(0, |used, body| used | self.body_colours[body]);
+            let colour = used.trailing_ones() as usize;
+
+            if colour >= MAX_COLOURS {
+                self.overflow.push(i);
+
+                continue;
+            }
+
+            if colour == self.colours.len() {
+                self.colours.push(Vec::new());
+            }
+
+            self.colours[colour].push(i);
+
+            dynamic_bodies().for_each(|body| self.body_colours[body] |= 1 << colour);
+        }
+    }
+
+    // bypasses Constraint::solve, every constraint is solved through compute_correction
+    pub(crate) fn solve(&mut self, constraints: &mut ConstraintSet, bodies: &mut BodySet, active: &[bool], dt: Precision) {
+        for colour in &self.colours {
+            let solve_one = |&i: &usize| -> SolveResult {
+                let mut lambda = constraints.lambda[i];
+
+                let result = constraints.constraints[i].as_deref()
+                    .filter(|_| active[i])
+                    .and_then(|constraint| constraint.compute_correction(bodies))
+                    .map(|correction| (correction.direction(), correction.compute_deltas(bodies, &mut lambda, dt)));
+
+                (lambda, result)
+            };
+
+            solve_batch(colour, &mut self.results, solve_one);
+
+            // no two results touch the same dynamic body, so the order they are applied in does not matter
+            for (&i, (lambda, result)) in colour.iter().zip(self.results.drain(..)) {
+                constraints.lambda[i] = lambda;
+
+                let Some((direction, deltas)) = result else { continue; };
+
+                constraints.direction[i] = Some(direction);
+
+                for delta in &deltas {
+                    bodies.apply_delta(delta);
+                }
+            }
+        }
+
+        solve_gauss_seidel(constraints, bodies, self.overflow.iter().copied(), active, dt);
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct JacobiSolver {
+    order: Vec<usize>,
+
+    pub(crate) body_counts: Vec<Precision>, // active constraints acting on each dynamic body
+    translations: Vec<Vector3<Precision>>,
+    rotations: Vec<Vector3<Precision>>,
+
+    results: Vec<SolveResult>
+}
+
+impl JacobiSolver {
+    pub(crate) fn prepare(&mut self, constraints: &ConstraintSet, bodies: &BodySet, active: &[bool]) {
+        let count = bodies.position.len();
+
+        self.order.clear();
+
+        self.body_counts.clear();
+        self.body_counts.resize(count, 0.0);
+        self.translations.clear();
+        self.translations.resize(count, Vector3::zeros());
+        self.rotations.clear();
+        self.rotations.resize(count, Vector3::zeros());
+
+        for (i, constraint) in constraints.iter_enabled() {
+            if !active[i] { continue; }
+
+            self.order.push(i);
+
+            for body in constraint.bodies() {
+                if bodies.has_finite_mass(*body) { self.body_counts[*body] += 1.0; }
+            }
+        }
+    }
+
+    pub(crate) fn solve(
+        &mut self,
+        constraints: &mut ConstraintSet,
+        bodies: &mut BodySet,
+        active: &[bool],
+        averaging: JacobiAveraging,
+        dt: Precision
+    ) {
+        let body_counts = &self.body_counts;
+        let mass_split = |body: usize| match averaging {
+            JacobiAveraging::ConstraintCount => 1.0,
+            JacobiAveraging::MassSplitting => body_counts[body].max(1.0)
+        };
+
+        // the mean only gives each body 1 / count of a plain correction, a split correction is already sized for it
+        let applied = |body: usize| match averaging {
+            JacobiAveraging::ConstraintCount => 1.0 / body_counts[body].max(1.0),
+            JacobiAveraging::MassSplitting => 1.0
+        };
+
+        let solve_one = |&i: &usize| -> SolveResult {
+            let mut lambda = constraints.lambda[i];
+
+            let result = constraints.constraints[i].as_deref()
+                .filter(|_| active[i])
+                .and_then(|constraint| constraint.compute_correction(bodies))
+                .map(|correction| (correction.direction(), correction.compute_split_deltas(bodies, &mut lambda, dt, mass_split, applied)));
+
+            (lambda, result)
+        };
+
+        solve_batch(&self.order, &mut self.results, solve_one);
+
+        // summed in slot order so the result does not depend on how the work was split
+        for (&i, (lambda, result)) in self.order.iter().zip(self.results.drain(..)) {
+            constraints.lambda[i] = lambda;
+
+            let Some((direction, deltas)) = result else { continue; };
+
+            constraints.direction[i] = Some(direction);
+
+            for delta in &deltas {
+                self.translations[delta.body] += delta.translation;
+                self.rotations[delta.body] += delta.rotation;
+            }
+        }
+
+        for (body, count) in self.body_counts.iter().enumerate() {
+            if *count == 0.0 { continue; }
+
+            let delta = BodyDelta {
+                body,
+
+                translation: self.translations[body] / *count,
+                rotation: self.rotations[body] / *count
+            };
+
+            bodies.apply_delta(&delta);
+
+            self.translations[body] = Vector3::zeros();
+            self.rotations[body] = Vector3::zeros();
+        }
+    }
+}
+
+// each thread gets one contiguous share of a large batch, every split point is a job handed between threads,
+// small batches and single threaded pools are solved in place
+#[cfg(feature = "parallel")]
+fn solve_batch(order: &[usize], results: &mut Vec<SolveResult>, solve_one: impl Fn(&usize) -> SolveResult + Send + Sync) {
+    if is_worth_splitting(order.len()) {
+        let share = order.len().div_ceil(rayon::current_num_threads()).max(MIN_PARALLEL_BATCH);
+
+        order.par_iter().with_min_len(share).map(solve_one).collect_into_vec(results);
+    } else {
+        results.clear();
+        results.extend(order.iter().map(solve_one));
+    }
+}
+
+#[cfg(feature = "parallel")]
+fn is_worth_splitting(batch: usize) -> bool {
+    rayon::current_num_threads() > 1 && batch >= 2 * MIN_PARALLEL_BATCH
+}
+
+#[cfg(not(feature = "parallel"))]
+fn solve_batch(order: &[usize], results: &mut Vec<SolveResult>, solve_one: impl Fn(&usize) -> SolveResult) {
+    results.clear();
+    results.extend(order.iter().map(solve_one));
+}
+
+// work handed to the pool from outside goes through its shared queue, which allocates a block every few
+// dozen jobs, so the parallel solvers move onto the pool once per sub step and hand batches on from there,
+// unless there are too few active constraints for any batch to be split
+#[cfg(feature = "parallel")]
+pub(crate) fn in_thread_pool(active_constraints: usize, op: impl FnOnce() + Send) {
+    if is_worth_splitting(active_constraints) {
+        rayon::scope(|_| op());
+    } else {
+        op();
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn in_thread_pool(_active_constraints: usize, op: impl FnOnce()) {
+    op();
+}
+
+pub(crate) fn solve_gauss_seidel(
+    constraints: &mut ConstraintSet,
+    bodies: &mut BodySet,
+    order: impl IntoIterator<Item = usize>,
+    active: &[bool],
+    dt: Precision
+) {
+    for i in order {
+        if !active[i] { continue; }
+
+        let Some(constraint) = constraints.constraints[i].as_mut() else { continue; };
+
+        if let Some(direction) = constraint.solve(bodies, &mut constraints.lambda[i], dt) {
+            constraints.direction[i] = Some(direction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{chain, Rope};
+    use crate::{ConstraintHandle, World};
+    use super::*;
+
+    fn settled_chain(averaging: JacobiAveraging) -> (World, Vec<ConstraintHandle>) {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 8, 8);
+
+        world.solver_mode = SolverMode::Jacobi(averaging);
+
+        let ropes = chain(&mut world, 5);
+
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+        }
+
+        (world, ropes)
+    }
+
+    fn max_error(world: &World, ropes: &[ConstraintHandle]) -> Precision {
+        ropes.iter()
+            .map(|&rope| world.constraint_ref::<Rope>(rope).unwrap().error(&world.bodies).abs())
+            .fold(0.0, Precision::max)
+    }
+
+    #[test]
+    fn jacobi_converges_when_averaging_by_constraint_count() {
+        let (world, ropes) = settled_chain(JacobiAveraging::ConstraintCount);
+
+        assert!(max_error(&world, &ropes) < 0.01, "error {}", max_error(&world, &ropes));
+    }
+
+    #[test]
+    fn jacobi_converges_with_mass_splitting() {
+        let (world, ropes) = settled_chain(JacobiAveraging::MassSplitting);
+
+        assert!(max_error(&world, &ropes) < 0.01, "error {}", max_error(&world, &ropes));
+    }
+
+    #[test]
+    fn jacobi_forces_carry_the_weight_below_each_rope() {
+        // the mean gives the two ends of a rope different shares of it, so the force is only within half a link
+        let offsets = [
+            (JacobiAveraging::ConstraintCount, 0.5 * 9.81),
+            (JacobiAveraging::MassSplitting, 0.0)
+        ];
+
+        for (averaging, offset) in offsets {
+            let (world, ropes) = settled_chain(averaging);
+
+            for (i, &rope) in ropes.iter().enumerate() {
+                let Some(ConstraintForce::Force(force)) = world.constraint_force(rope) else { panic!("expected a force"); };
+                let weight = (ropes.len() - i) as Precision * 9.81;
+
+                assert!((force.norm() - weight).abs() < offset + 0.02 * weight, "{averaging:?} rope {i}: {} instead of {weight}", force.norm());
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use nalgebra::{Point3, UnitVector3};
+use crate::{smallvec, BodyHandle, BodySet, BodyVec, Constraint, ConstraintHandle, CorrectionData, Precision, RigidBodyDesc, World, EPSILON};
+
+// keeps the centres of mass of two bodies a fixed distance apart, the real joints live in fizix-constraints
+pub(crate) struct Rope {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+
+    pub length: Precision,
+    pub compliance: Precision
+}
+
+impl Rope {
+    pub fn new(body_a: BodyHandle, body_b: BodyHandle, length: Precision) -> Self {
+        Self { body_a, body_b, length, compliance: 0.0 }
+    }
+
+    pub fn error(&self, bodies: &BodySet) -> Precision {
+        (bodies.center_of_mass(*self.body_b) - bodies.center_of_mass(*self.body_a)).norm() - self.length
+    }
+}
+
+// unit mass links hanging straight down from a fixed anchor, one unit rope each, ordered from the top
+pub(crate) fn chain(world: &mut World, links: usize) -> Vec<ConstraintHandle> {
+    let mut last = world.create_body(RigidBodyDesc::fixed());
+
+    (0..links).map(|i| {
+        let link = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(0.0, -(i as Precision + 1.0), 0.0)));
+        let rope = world.add_constraint(Rope::new(last, link, 1.0)).unwrap();
+
+        last = link;
+
+        rope
+    }).collect()
+}
+
+impl Constraint for Rope {
+    fn bodies(&self) -> BodyVec<BodyHandle> {
+        smallvec![self.body_a, self.body_b]
+    }
+
+    fn compute_correction(&self, bodies: &BodySet) -> Option<CorrectionData> {
+        let difference = bodies.center_of_mass(*self.body_b) - bodies.center_of_mass(*self.body_a);
+        let distance = difference.norm();
+        let error = distance - self.length;
+
+        if distance < EPSILON || error.abs() < EPSILON { return None; }
+
+        let normal = UnitVector3::new_unchecked(difference / distance);
+
+        Some(CorrectionData::Translational {
+            handles: smallvec![self.body_a, self.body_b],
+            relative_points: smallvec![Point3::origin(), Point3::origin()],
+            normals: smallvec![-normal, normal],
+
+            error, alpha: self.compliance
+        })
+    }
+}
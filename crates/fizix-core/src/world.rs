@@ -1,27 +1,103 @@
-use crate::{BodyHandle, BodySet, Constraint, Precision};
-use itertools::izip;
+use crate::{math, solve_gauss_seidel, BodyHandle, BodySet, BodyType, BreakThreshold, ColouredSolver, Constraint, ConstraintForce, ConstraintHandle, ConstraintSet, Damping, Event, GravityField, GyroscopicMode, in_thread_pool, Islands, JacobiSolver, LockedAxes, Precision, RigidBodyDesc, SleepSettings, SolverMode, UniformGravity};
 use nalgebra::{Matrix3, Point3, UnitQuaternion, Vector3};
 
 pub struct World {
-    pub bodies: BodySet,
-    pub constraints: Vec<Box<dyn Constraint>>,
+    pub(crate) bodies: BodySet, // read only outside the crate, removing a body has to go through the world
+    pub(crate) constraints: ConstraintSet, // read only outside the crate as well
 
-    gravity: Vector3<Precision>,
+    pub sleep_settings: SleepSettings,
+    pub default_damping: Damping, // given to bodies as they are added
+    pub gyroscopic_mode: GyroscopicMode,
+    pub solver_mode: SolverMode,
+
+    gravity: Box<dyn GravityField>,
+
+    events: Vec<Event>, // kept until drained, across steps
 
     sub_steps: usize,
-    constraint_iterations: usize
+    constraint_iterations: usize,
+
+    last_sub_dt: Precision, // of the last step, for reading constraint forces back
+
+    // scratch buffers reused every step so that stepping does not allocate
+    islands: Islands,
+    coloured_solver: ColouredSolver,
+    jacobi_solver: JacobiSolver,
+    active: Vec<bool>,
+    disturbed: Vec<bool>
 }
 
 impl World {
     pub fn new(gravity: Vector3<Precision>, sub_steps: usize, constraint_iterations: usize) -> Self {
         Self {
             bodies: BodySet::new(),
-            constraints: Vec::new(),
+            constraints: ConstraintSet::new(),
+
+            sleep_settings: SleepSettings::default(),
+            default_damping: Damping::default(),
+            gyroscopic_mode: GyroscopicMode::default(),
+            solver_mode: SolverMode::default(),
+
+            gravity: Box::new(UniformGravity(gravity)),
+
+            events: Vec::new(),
+
+            sub_steps, constraint_iterations,
+
+            last_sub_dt: 0.0,
+
+            islands: Islands::default(),
+            coloured_solver: ColouredSolver::default(),
+            jacobi_solver: JacobiSolver::default(),
+            active: Vec::new(),
+            disturbed: Vec::new()
+        }
+    }
 
-            gravity, sub_steps, constraint_iterations
+    // resting bodies were only resting under the old gravity, so everything wakes
+
+    pub fn set_gravity(&mut self, gravity: Vector3<Precision>) {
+        self.set_gravity_field(UniformGravity(gravity));
+    }
+
+    pub fn set_gravity_field(&mut self, field: impl GravityField + 'static) {
+        self.gravity = Box::new(field);
+
+        for i in 0..self.bodies.position.len() {
+            if self.bodies.is_alive(i) { self.bodies.wake(i); }
         }
     }
 
+    #[inline]
+    pub fn bodies(&self) -> &BodySet {
+        &self.bodies
+    }
+
+    #[inline]
+    pub fn constraints(&self) -> &ConstraintSet {
+        &self.constraints
+    }
+
+    // compare between peers to catch desyncs, equal states always give equal hashes
+    #[inline]
+    pub fn state_hash(&self) -> u64 {
+        self.bodies.state_hash()
+    }
+
+    #[inline]
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn drain_events(&mut self) -> impl Iterator<Item = Event> + '_ {
+        self.events.drain(..)
+    }
+
+    #[inline]
+    pub fn gravity_at(&self, point: &Point3<Precision>) -> Vector3<Precision> {
+        self.gravity.acceleration_at(point)
+    }
+
     pub fn add_body(
         &mut self,
         position: Point3<Precision>,
@@ -29,86 +105,997 @@ impl World {
         mass: Precision,
         inertia_tensor: Matrix3<Precision>
     ) -> BodyHandle {
-        let rotation = orientation.to_rotation_matrix();
+        let body_type = if mass.is_finite() && mass > 0.0 { BodyType::Dynamic } else { BodyType::Static };
+
+        self.create_body(RigidBodyDesc {
+            position, orientation,
+            mass, inertia_tensor,
+            body_type,
+
+            ..Default::default()
+        })
+    }
 
-        let is_mass_valid = mass.is_finite() && mass > 0.0;
-        let inverse_mass = if is_mass_valid { 1.0 / mass } else { 0.0 };
+    pub fn create_body(&mut self, desc: RigidBodyDesc) -> BodyHandle {
+        let is_mass_valid = desc.body_type != BodyType::Static && desc.mass.is_finite() && desc.mass > 0.0;
+        let inverse_mass = if is_mass_valid { 1.0 / desc.mass } else { 0.0 };
         let inverse_inertia_tensor = if is_mass_valid {
-            inertia_tensor.try_inverse().unwrap_or(Matrix3::zeros())
+            desc.inertia_tensor.try_inverse().unwrap_or(Matrix3::zeros())
         } else {
             Matrix3::zeros()
         };
-        let inverse_inertia_tensor_world = rotation * inverse_inertia_tensor * rotation.transpose();
 
-        self.bodies.position.push(position);
-        self.bodies.orientation.push(orientation);
+        let handle = self.bodies.insert(desc.position, desc.orientation, inverse_mass, inverse_inertia_tensor);
+        let i = *handle;
+
+        // a dynamic body without valid mass is left static by insert
+        if desc.body_type == BodyType::Kinematic {
+            self.bodies.body_type[i] = BodyType::Kinematic;
+        }
+
+        if self.bodies.body_type[i] != BodyType::Static {
+            self.bodies.linear_velocity[i] = desc.linear_velocity;
+            self.bodies.angular_velocity[i] = desc.angular_velocity;
+        }
+
+        self.bodies.local_center_of_mass[i] = desc.local_center_of_mass;
+        self.bodies.locked_axes[i] = desc.locked_axes;
+        self.bodies.gravity_scale[i] = desc.gravity_scale;
+        self.bodies.ccd_enabled[i] = desc.ccd_enabled;
+
+        self.bodies.damping[i] = desc.damping.unwrap_or(self.default_damping);
+
+        self.bodies.sleeping[i] = desc.sleeping && self.bodies.has_finite_mass(i);
+        self.bodies.user_data[i] = desc.user_data;
+        self.bodies.apply_axis_locks(i);
+
+        handle
+    }
+
+    // returns the constraints that were attached to the body, or None if the handle is stale
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<Vec<Box<dyn Constraint>>> {
+        if !self.bodies.remove(handle) { return None; }
+
+        let attached = self.constraints.iter()
+            .filter(|(_, constraint)| constraint.bodies().contains(&handle))
+            .map(|(constraint_handle, _)| constraint_handle)
+            .collect::<Vec<_>>();
+
+        // whatever hung off the removed body has to react to losing it
+        let removed = attached.into_iter()
+            .filter_map(|constraint_handle| self.remove_constraint(constraint_handle))
+            .collect();
+
+        Some(removed)
+    }
+
+    #[inline]
+    pub fn contains_body(&self, handle: BodyHandle) -> bool {
+        self.bodies.contains(handle)
+    }
+
+    #[inline]
+    pub fn body_user_data(&self, handle: BodyHandle) -> Option<u64> {
+        self.bodies.contains(handle).then(|| self.bodies.user_data[*handle])
+    }
+
+    // the following setters return false for stale handles
+
+    pub fn set_body_user_data(&mut self, handle: BodyHandle, user_data: u64) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        self.bodies.user_data[*handle] = user_data;
+
+        true
+    }
+
+    pub fn set_body_damping(&mut self, handle: BodyHandle, damping: Damping) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        self.bodies.damping[*handle] = damping;
+
+        true
+    }
+
+    // poses stay in terms of the body origin, only dynamics happen about the centre of mass
+    pub fn set_body_center_of_mass(&mut self, handle: BodyHandle, local_center_of_mass: Vector3<Precision>) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        self.bodies.local_center_of_mass[*handle] = local_center_of_mass;
+
+        true
+    }
+
+    // 0 leaves the body floating, negative values make it fall upwards
+    pub fn set_body_gravity_scale(&mut self, handle: BodyHandle, gravity_scale: Precision) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        self.bodies.gravity_scale[*handle] = gravity_scale;
+        self.bodies.wake(*handle);
+
+        true
+    }
+
+    pub fn set_body_ccd_enabled(&mut self, handle: BodyHandle, ccd_enabled: bool) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        self.bodies.ccd_enabled[*handle] = ccd_enabled;
+
+        true
+    }
+
+    pub fn set_body_locked_axes(&mut self, handle: BodyHandle, locked_axes: LockedAxes) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        self.bodies.locked_axes[*handle] = locked_axes;
+        self.bodies.apply_axis_locks(*handle);
+
+        true
+    }
+
+    // forces and torques accumulate until the end of the next step
+
+    pub fn apply_force(&mut self, handle: BodyHandle, force: Vector3<Precision>) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        self.bodies.force[*handle] += force;
+        self.bodies.wake(*handle);
+
+        true
+    }
+
+    pub fn apply_force_at_point(&mut self, handle: BodyHandle, force: Vector3<Precision>, point: Point3<Precision>) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        let i = *handle;
+
+        let torque = (point - self.bodies.center_of_mass(i)).cross(&force);
+
+        self.bodies.force[i] += force;
+        self.bodies.torque[i] += torque;
+        self.bodies.wake(i);
+
+        true
+    }
+
+    pub fn apply_torque(&mut self, handle: BodyHandle, torque: Vector3<Precision>) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        self.bodies.torque[*handle] += torque;
+        self.bodies.wake(*handle);
+
+        true
+    }
+
+    // impulses change velocity immediately, so they count once no matter the sub step count
+
+    pub fn apply_impulse(&mut self, handle: BodyHandle, impulse: Vector3<Precision>) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        let i = *handle;
+
+        if self.bodies.has_finite_mass(i) {
+            let linear_delta = self.bodies.effective_inverse_mass(i).component_mul(&impulse);
+
+            self.bodies.linear_velocity[i] += linear_delta;
+            self.bodies.wake(i);
+        }
+
+        true
+    }
+
+    pub fn apply_impulse_at_point(&mut self, handle: BodyHandle, impulse: Vector3<Precision>, point: Point3<Precision>) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        let i = *handle;
+
+        if self.bodies.has_finite_mass(i) {
+            let angular_impulse = (point - self.bodies.center_of_mass(i)).cross(&impulse);
+
+            let linear_delta = self.bodies.effective_inverse_mass(i).component_mul(&impulse);
+            let angular_delta = self.bodies.effective_inverse_inertia_tensor(i) * angular_impulse;
+
+            self.bodies.linear_velocity[i] += linear_delta;
+            self.bodies.angular_velocity[i] += angular_delta;
+            self.bodies.wake(i);
+        }
+
+        true
+    }
+
+    // the rest of the body's island wakes with it at the start of the next step
+    pub fn wake_body(&mut self, handle: BodyHandle) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        self.bodies.wake(*handle);
+
+        true
+    }
+
+    #[inline]
+    pub fn is_body_sleeping(&self, handle: BodyHandle) -> bool {
+        self.bodies.contains(handle) && self.bodies.is_sleeping(*handle)
+    }
+
+    pub fn islands(&self) -> Vec<Vec<BodyHandle>> {
+        let mut islands = Islands::default();
+
+        islands.build(&self.bodies, &self.constraints);
+
+        islands.iter()
+            .map(|island| island.iter().map(|&i| self.bodies.handle(i)).collect())
+            .collect()
+    }
+
+    pub fn set_body_type(&mut self, handle: BodyHandle, body_type: BodyType) -> bool {
+        if !self.bodies.contains(handle) { return false; }
+
+        let i = *handle;
+
+        self.bodies.body_type[i] = body_type;
+        self.bodies.kinematic_target[i] = None;
+
+        if body_type == BodyType::Static {
+            self.bodies.linear_velocity[i] = Vector3::zeros();
+            self.bodies.angular_velocity[i] = Vector3::zeros();
+        }
+
+        true
+    }
+
+    // the body reaches the pose at the end of the next step and is held there until told otherwise
+    pub fn set_kinematic_target(
+        &mut self,
+        handle: BodyHandle,
+        position: Point3<Precision>,
+        orientation: UnitQuaternion<Precision>
+    ) -> bool {
+        if !self.bodies.contains(handle) || !self.bodies.is_kinematic(*handle) { return false; }
+
+        self.bodies.kinematic_target[*handle] = Some((position, orientation));
+
+        true
+    }
+
+    // the body keeps moving at this velocity, replacing any pose target
+    pub fn set_kinematic_velocity(
+        &mut self,
+        handle: BodyHandle,
+        linear_velocity: Vector3<Precision>,
+        angular_velocity: Vector3<Precision>
+    ) -> bool {
+        if !self.bodies.contains(handle) || !self.bodies.is_kinematic(*handle) { return false; }
+
+        let i = *handle;
+
+        self.bodies.kinematic_target[i] = None;
+        self.bodies.linear_velocity[i] = linear_velocity;
+        self.bodies.angular_velocity[i] = angular_velocity;
+
+        true
+    }
+
+    // None if the constraint references a removed or invalid body
+    pub fn add_constraint(&mut self, constraint: impl Constraint + 'static) -> Option<ConstraintHandle> {
+        if !constraint.bodies().iter().all(|&body| self.bodies.contains(body)) { return None; }
+
+        let handle = self.constraints.insert(Box::new(constraint));
+
+        self.wake_constraint_bodies(handle);
+
+        Some(handle)
+    }
+
+    // returns the constraint, or None if the handle is stale
+    pub fn remove_constraint(&mut self, handle: ConstraintHandle) -> Option<Box<dyn Constraint>> {
+        self.wake_constraint_bodies(handle);
+
+        self.constraints.remove(handle)
+    }
+
+    #[inline]
+    pub fn contains_constraint(&self, handle: ConstraintHandle) -> bool {
+        self.constraints.contains(handle)
+    }
 
-        self.bodies.last_position.push(position);
-        self.bodies.last_orientation.push(orientation);
+    #[inline]
+    pub fn is_constraint_enabled(&self, handle: ConstraintHandle) -> bool {
+        self.constraints.contains(handle) && self.constraints.is_enabled(*handle)
+    }
+
+    // disabled constraints keep their slot and parameters but are skipped by the solver
+    pub fn set_constraint_enabled(&mut self, handle: ConstraintHandle, enabled: bool) -> bool {
+        if !self.constraints.contains(handle) { return false; }
+
+        if self.constraints.enabled[*handle] != enabled {
+            self.constraints.enabled[*handle] = enabled;
+            self.wake_constraint_bodies(handle);
+        }
+
+        true
+    }
+
+    // None makes the constraint unbreakable
+    pub fn set_constraint_break_threshold(&mut self, handle: ConstraintHandle, threshold: Option<BreakThreshold>) -> bool {
+        if !self.constraints.contains(handle) { return false; }
+
+        self.constraints.break_threshold[*handle] = threshold;
+
+        true
+    }
+
+    // opaque value for mapping the constraint back to game side data, also reported when it breaks
+    #[inline]
+    pub fn constraint_user_data(&self, handle: ConstraintHandle) -> Option<u64> {
+        self.constraints.contains(handle).then(|| self.constraints.user_data[*handle])
+    }
 
-        self.bodies.linear_velocity.push(Vector3::zeros());
-        self.bodies.angular_velocity.push(Vector3::zeros());
+    pub fn set_constraint_user_data(&mut self, handle: ConstraintHandle, user_data: u64) -> bool {
+        if !self.constraints.contains(handle) { return false; }
 
-        self.bodies.force.push(Vector3::zeros());
-        self.bodies.torque.push(Vector3::zeros());
+        self.constraints.user_data[*handle] = user_data;
 
-        self.bodies.inverse_mass.push(inverse_mass);
-        self.bodies.inverse_inertia_tensor_local.push(inverse_inertia_tensor);
+        true
+    }
+
+    #[inline]
+    pub fn constraint(&self, handle: ConstraintHandle) -> Option<&(dyn Constraint + 'static)> {
+        self.constraints.get(handle)
+    }
+
+    // None when the handle is stale or the constraint is not a T
+    #[inline]
+    pub fn constraint_ref<T: Constraint>(&self, handle: ConstraintHandle) -> Option<&T> {
+        self.constraints.downcast_ref(handle)
+    }
+
+    // the bodies are woken since the new parameters may pull them out of rest, a wrong T leaves them asleep
+    pub fn constraint_mut<T: Constraint>(&mut self, handle: ConstraintHandle) -> Option<&mut T> {
+        self.constraints.downcast_ref::<T>(handle)?;
+        self.wake_constraint_bodies(handle);
 
-        self.bodies.inverse_inertia_tensor_world.push(inverse_inertia_tensor_world);
+        self.constraints.downcast_mut(handle)
+    }
 
-        BodyHandle::new(self.bodies.position.len() - 1)
+    // force or torque on the first body only during the last sub step, the other bodies feel the reaction
+    // None for stale handles and for constraints that corrected nothing, so the kind of force stays known
+    #[inline]
+    pub fn constraint_force(&self, handle: ConstraintHandle) -> Option<ConstraintForce> {
+        self.constraints.force(handle, self.last_sub_dt)
     }
 
-    pub fn add_constraint(&mut self, constraint: impl Constraint + 'static) {
-        self.constraints.push(Box::new(constraint));
+    fn wake_constraint_bodies(&mut self, handle: ConstraintHandle) {
+        let Some(constraint) = self.constraints.get(handle) else { return; };
+
+        for body in constraint.bodies() {
+            if self.bodies.contains(body) { self.bodies.wake(*body); }
+        }
     }
 
     pub fn step(&mut self, dt: Precision) {
         let sub_dt = dt / self.sub_steps as Precision;
         let inv_dt = 1.0 / sub_dt;
 
-        for _ in 0..self.sub_steps {
+        self.last_sub_dt = sub_dt;
+
+        let mut islands = std::mem::take(&mut self.islands);
+
+        islands.build(&self.bodies, &self.constraints);
+
+        if self.sleep_settings.enabled {
+            self.wake_disturbed_islands(&islands);
+        } else {
+            islands.bodies().iter().for_each(|&i| self.bodies.wake(i));
+        }
+
+        // constraints between sleeping or immovable bodies are skipped entirely
+        let mut active = std::mem::take(&mut self.active);
+
+        active.clear();
+        active.resize(self.constraints.constraints.len(), false);
+
+        for (i, constraint) in self.constraints.iter_enabled() {
+            active[i] = constraint.bodies().iter().any(|body| {
+                self.bodies.has_finite_mass(**body) && !self.bodies.is_sleeping(**body)
+            });
+        }
+
+        let active_count = active.iter().filter(|is_active| **is_active).count();
+
+        self.prepare_solver(&active);
+
+        for sub_step in 0..self.sub_steps {
             // integration
             for i in 0..self.bodies.position.len() {
-                if !self.bodies.has_finite_mass(i) { continue; }
+                if !self.bodies.is_alive(i) { continue; }
+
+                if self.bodies.is_kinematic(i) {
+                    self.integrate_kinematic(i, self.sub_steps - sub_step, sub_dt);
+
+                    continue;
+                }
+
+                if !self.bodies.has_finite_mass(i) || self.bodies.is_sleeping(i) { continue; }
 
                 self.bodies.last_position[i] = self.bodies.position[i];
                 self.bodies.last_orientation[i] = self.bodies.orientation[i];
 
-                let linear_acc = self.gravity + self.bodies.inverse_mass[i] * self.bodies.force[i];
-                let angular_acc = self.bodies.inverse_inertia_tensor_world[i] * self.bodies.torque[i];
+                let gravity = self.gravity.acceleration_at(&self.bodies.center_of_mass(i)) * self.bodies.gravity_scale[i];
 
-                self.bodies.force[i].fill(0.0);
-                self.bodies.torque[i].fill(0.0);
+                let linear_acc = gravity + self.bodies.inverse_mass[i] * self.bodies.force[i];
+                let angular_acc = self.bodies.inverse_inertia_tensor_world[i] * self.bodies.torque[i];
                 
                 self.bodies.linear_velocity[i] += linear_acc * sub_dt;
                 self.bodies.angular_velocity[i] += angular_acc * sub_dt;
 
+                self.bodies.apply_gyroscopic_torque(i, self.gyroscopic_mode, sub_dt);
+
+                self.bodies.apply_damping(i, sub_dt);
+                self.bodies.apply_axis_locks(i);
+
                 self.bodies.position[i] += self.bodies.linear_velocity[i] * sub_dt;
                 
                 self.bodies.apply_rotation_delta(i, self.bodies.angular_velocity[i] * sub_dt);
                 self.bodies.update_derived_data(i);
             }
 
-            // constraint solve
-            let mut lambdas = vec![0.0; self.constraints.len()];
+            // constraint solve, the force read back afterwards only covers the last sub step
+            self.constraints.lambda.fill(0.0);
+            self.constraints.direction.fill(None);
 
-            for _ in 0..self.constraint_iterations {
-                for (constraint, lambda) in izip!(&mut self.constraints, &mut lambdas) {
-                    constraint.solve(&mut self.bodies, lambda, sub_dt);
-                }
+            match self.solver_mode {
+                SolverMode::GaussSeidel => for _ in 0..self.constraint_iterations {
+                    let order = 0..self.constraints.constraints.len();
+
+                    solve_gauss_seidel(&mut self.constraints, &mut self.bodies, order, &active, sub_dt);
+                },
+                SolverMode::Coloured => in_thread_pool(active_count, || for _ in 0..self.constraint_iterations {
+                    self.coloured_solver.solve(&mut self.constraints, &mut self.bodies, &active, sub_dt);
+                }),
+                SolverMode::Jacobi(averaging) => in_thread_pool(active_count, || for _ in 0..self.constraint_iterations {
+                    self.jacobi_solver.solve(&mut self.constraints, &mut self.bodies, &active, averaging, sub_dt);
+                })
+            }
+
+            // broken constraints no longer count towards the bodies they shared
+            if self.break_constraints(&mut active, sub_dt) {
+                self.prepare_solver(&active);
             }
 
-            // velocity update
+            // velocity update, kinematic bodies included so the solver sees how fast they really move
             for i in 0..self.bodies.position.len() {
-                if !self.bodies.has_finite_mass(i) { continue; }
+                if !self.bodies.is_alive(i) || self.bodies.is_sleeping(i) { continue; }
+                if !self.bodies.has_finite_mass(i) && !self.bodies.is_kinematic(i) { continue; }
 
                 let delta_q = self.bodies.orientation[i] * self.bodies.last_orientation[i].conjugate();
 
-                self.bodies.linear_velocity[i] = (self.bodies.position[i] - self.bodies.last_position[i]) * inv_dt;
-                self.bodies.angular_velocity[i] = delta_q.scaled_axis() * inv_dt;
+                self.bodies.linear_velocity[i] = (self.bodies.center_of_mass(i) - self.bodies.last_center_of_mass(i)) * inv_dt;
+                self.bodies.angular_velocity[i] = math::quaternion_scaled_axis(&delta_q) * inv_dt;
             }
         }
+
+        // accumulated forces act for the whole step, not just the first sub step
+        self.bodies.force.iter_mut().for_each(|force| force.fill(0.0));
+        self.bodies.torque.iter_mut().for_each(|torque| torque.fill(0.0));
+
+        if self.sleep_settings.enabled {
+            self.update_sleep(&islands, dt);
+        }
+
+        self.islands = islands;
+        self.active = active;
+    }
+
+    fn prepare_solver(&mut self, active: &[bool]) {
+        match self.solver_mode {
+            SolverMode::GaussSeidel => {},
+            SolverMode::Coloured => self.coloured_solver.colour(&self.constraints, &self.bodies, active),
+            SolverMode::Jacobi(_) => self.jacobi_solver.prepare(&self.constraints, &self.bodies, active)
+        }
+    }
+
+    // returns whether any constraint broke
+    fn break_constraints(&mut self, active: &mut [bool], sub_dt: Precision) -> bool {
+        let mut any_broken = false;
+
+        for (i, is_active) in active.iter_mut().enumerate() {
+            if !*is_active { continue; }
+
+            let Some(threshold) = self.constraints.break_threshold[i] else { continue; };
+            let handle = self.constraints.handle(i);
+            let Some(force) = self.constraints.force(handle, sub_dt) else { continue; };
+
+            if !threshold.is_exceeded_by(&force) { continue; }
+
+            *is_active = false;
+            self.set_constraint_enabled(handle, false);
+
+            let user_data = self.constraints.user_data[i];
+
+            self.events.push(Event::JointBroken { constraint: handle, force, user_data });
+
+            any_broken = true;
+        }
+
+        any_broken
+    }
+
+    // an island wakes when any of its bodies is awake, pushed by a force or dragged by a moving kinematic body
+    fn wake_disturbed_islands(&mut self, islands: &Islands) {
+        let mut disturbed = std::mem::take(&mut self.disturbed);
+
+        disturbed.clear();
+        disturbed.extend((0..self.bodies.position.len()).map(|i| {
+            self.bodies.is_alive(i) && (!self.bodies.is_sleeping(i) ||
+                self.bodies.force[i] != Vector3::zeros() || self.bodies.torque[i] != Vector3::zeros())
+        }));
+
+        for (_, constraint) in self.constraints.iter_enabled() {
+            let bodies = constraint.bodies();
+
+            if bodies.iter().any(|body| self.bodies.is_kinematic_moving(**body)) {
+                bodies.iter().for_each(|body| disturbed[**body] = true);
+            }
+        }
+
+        for island in islands.iter() {
+            if island.iter().any(|&i| disturbed[i]) && island.iter().any(|&i| self.bodies.is_sleeping(i)) {
+                island.iter().for_each(|&i| self.bodies.wake(i));
+            }
+        }
+
+        self.disturbed = disturbed;
+    }
+
+    fn update_sleep(&mut self, islands: &Islands, dt: Precision) {
+        let linear_threshold_sq = self.sleep_settings.linear_threshold * self.sleep_settings.linear_threshold;
+        let angular_threshold_sq = self.sleep_settings.angular_threshold * self.sleep_settings.angular_threshold;
+
+        for island in islands.iter() {
+            if island.iter().all(|&i| self.bodies.is_sleeping(i)) { continue; }
+
+            for &i in island {
+                let is_resting = self.bodies.linear_velocity[i].norm_squared() < linear_threshold_sq &&
+                    self.bodies.angular_velocity[i].norm_squared() < angular_threshold_sq;
+
+                self.bodies.sleep_timer[i] = if is_resting { self.bodies.sleep_timer[i] + dt } else { 0.0 };
+            }
+
+            if island.iter().any(|&i| self.bodies.sleep_timer[i] < self.sleep_settings.time_to_sleep) { continue; }
+
+            for &i in island {
+                self.bodies.sleeping[i] = true;
+
+                self.bodies.linear_velocity[i] = Vector3::zeros();
+                self.bodies.angular_velocity[i] = Vector3::zeros();
+            }
+        }
+    }
+
+    fn integrate_kinematic(&mut self, i: usize, remaining_sub_steps: usize, sub_dt: Precision) {
+        self.bodies.last_position[i] = self.bodies.position[i];
+        self.bodies.last_orientation[i] = self.bodies.orientation[i];
+
+        match self.bodies.kinematic_target[i] {
+            Some((position, orientation)) => {
+                // cover an equal share of what is left, the last sub step lands exactly on the target so the
+                // body stops counting as moving instead of being a rounding error away forever
+                if remaining_sub_steps <= 1 {
+                    self.bodies.position[i] = position;
+                    self.bodies.orientation[i] = orientation;
+                } else {
+                    let t = 1.0 / remaining_sub_steps as Precision;
+
+                    self.bodies.position[i] = self.bodies.position[i].coords.lerp(&position.coords, t).into();
+                    self.bodies.orientation[i] = math::quaternion_slerp(&self.bodies.orientation[i], &orientation, t);
+                }
+            },
+            None => {
+                self.bodies.position[i] += self.bodies.linear_velocity[i] * sub_dt;
+
+                self.bodies.apply_rotation_delta(i, self.bodies.angular_velocity[i] * sub_dt);
+            }
+        }
+
+        self.bodies.update_derived_data(i);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{chain, Rope};
+    use crate::{BodyVec, CorrectionData, JacobiAveraging};
+    use super::*;
+
+    #[test]
+    fn stale_body_handles_are_rejected() {
+        let mut world = World::new(Vector3::zeros(), 4, 1);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let removed = world.create_body(RigidBodyDesc::dynamic());
+        let rope = world.add_constraint(Rope::new(anchor, removed, 1.0)).unwrap();
+
+        assert_eq!(world.remove_body(removed).map(|detached| detached.len()), Some(1));
+        assert!(!world.contains_constraint(rope));
+        assert!(world.remove_body(removed).is_none());
+
+        let reused = world.create_body(RigidBodyDesc::dynamic().with_user_data(7));
+
+        assert_eq!(*reused, *removed);
+        assert!(!world.contains_body(removed));
+        assert_eq!(world.body_user_data(removed), None);
+        assert_eq!(world.body_user_data(reused), Some(7));
+        assert!(!world.apply_impulse(removed, Vector3::x()));
+        assert!(!world.set_body_user_data(removed, 1));
+        assert!(world.add_constraint(Rope::new(anchor, removed, 1.0)).is_none());
+        assert!(world.add_constraint(Rope::new(anchor, reused, 1.0)).is_some());
+    }
+
+    #[test]
+    fn removing_constraints_wakes_their_bodies() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 4, 1);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let body = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(0.0, -1.0, 0.0)));
+        let rope = world.add_constraint(Rope::new(anchor, body, 1.0)).unwrap();
+
+        // adding the rope woke the body
+        world.bodies.sleeping[*body] = true;
+
+        assert!(world.remove_constraint(rope).is_some());
+        assert!(!world.bodies.is_sleeping(*body));
+        assert!(world.constraints().is_empty());
+        assert_eq!(world.constraints().iter().count(), 0);
+    }
+
+    #[test]
+    fn constraint_user_data_belongs_to_the_slot() {
+        let mut world = World::new(Vector3::zeros(), 4, 1);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let body = world.create_body(RigidBodyDesc::dynamic());
+
+        let removed = world.add_constraint(Rope::new(anchor, body, 1.0)).unwrap();
+
+        assert_eq!(world.constraint_user_data(removed), Some(0));
+        assert!(world.set_constraint_user_data(removed, 7));
+        assert_eq!(world.constraint_user_data(removed), Some(7));
+
+        world.remove_constraint(removed);
+
+        let reused = world.add_constraint(Rope::new(anchor, body, 1.0)).unwrap();
+
+        assert_eq!(*reused, *removed);
+        assert_eq!(world.constraint_user_data(removed), None);
+        assert_eq!(world.constraint_user_data(reused), Some(0));
+        assert!(!world.set_constraint_user_data(removed, 1));
+    }
+
+    // velocity of a free 2 kg body after one step with a force and an impulse, for the given sub step count
+    fn pushed_velocity(sub_steps: usize) -> (Vector3<Precision>, Vector3<Precision>) {
+        let mut world = World::new(Vector3::zeros(), sub_steps, 1);
+        let body = world.create_body(RigidBodyDesc::dynamic().with_mass(2.0, Matrix3::identity() * 2.0));
+
+        world.apply_force(body, Vector3::new(6.0, 0.0, 0.0));
+        world.apply_torque(body, Vector3::new(0.0, 0.0, 3.0));
+        world.apply_impulse(body, Vector3::new(0.0, 1.0, 0.0));
+        world.step(0.5);
+
+        (world.bodies.linear_velocity(body).unwrap(), world.bodies.angular_velocity(body).unwrap())
+    }
+
+    #[test]
+    fn forces_and_impulses_do_not_depend_on_sub_steps() {
+        // 6 N on 2 kg for half a second plus a 1 Ns impulse, 3 Nm on 2 kg m^2
+        let expected_linear = Vector3::new(1.5, 0.5, 0.0);
+        let expected_angular = Vector3::new(0.0, 0.0, 0.75);
+
+        for sub_steps in [1, 4, 16] {
+            let (linear, angular) = pushed_velocity(sub_steps);
+
+            assert!((linear - expected_linear).norm() < 1e-4, "{sub_steps} sub steps: {linear}");
+            assert!((angular - expected_angular).norm() < 1e-4, "{sub_steps} sub steps: {angular}");
+        }
+    }
+
+    #[test]
+    fn forces_only_last_one_step() {
+        let mut world = World::new(Vector3::zeros(), 4, 1);
+        let body = world.create_body(RigidBodyDesc::dynamic());
+
+        world.apply_force(body, Vector3::x());
+        world.step(1.0);
+        world.step(1.0);
+
+        assert!((world.bodies.linear_velocity(body).unwrap() - Vector3::x()).norm() < 1e-4);
+    }
+
+    #[test]
+    fn constraint_mut_only_wakes_on_a_matching_type() {
+        struct Other;
+
+        impl Constraint for Other {
+            fn bodies(&self) -> BodyVec<BodyHandle> {
+                BodyVec::new()
+            }
+
+            fn compute_correction(&self, _: &BodySet) -> Option<CorrectionData> {
+                None
+            }
+        }
+
+        let mut world = World::new(Vector3::zeros(), 1, 1);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let body = world.create_body(RigidBodyDesc::dynamic());
+        let rope = world.add_constraint(Rope::new(anchor, body, 1.0)).unwrap();
+
+        world.bodies.sleeping[*body] = true;
+
+        assert!(world.constraint_mut::<Other>(rope).is_none());
+        assert!(world.is_body_sleeping(body));
+
+        world.constraint_mut::<Rope>(rope).unwrap().length = 2.0;
+
+        assert!(!world.is_body_sleeping(body));
+        assert_eq!(world.constraint_ref::<Rope>(rope).map(|rope| rope.length), Some(2.0));
+    }
+
+    #[test]
+    fn constraint_force_holds_up_a_hanging_body() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 8, 4);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let body = world.create_body(RigidBodyDesc::dynamic()
+            .with_position(Point3::new(0.0, -1.0, 0.0))
+            .with_mass(2.0, Matrix3::identity()));
+        let rope = world.add_constraint(Rope::new(anchor, body, 1.0)).unwrap();
+
+        assert_eq!(world.constraint_force(rope), None);
+
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+
+        // the anchor is the first body, the rope pulls it down with the weight of the body
+        let Some(ConstraintForce::Force(force)) = world.constraint_force(rope) else { panic!("expected a force"); };
+
+        assert!((force - Vector3::new(0.0, -19.62, 0.0)).norm() < 0.05);
+    }
+
+    #[test]
+    fn constraint_force_is_none_without_a_correction() {
+        let mut world = World::new(Vector3::zeros(), 4, 1);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let body = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(0.0, -1.0, 0.0)));
+        let rope = world.add_constraint(Rope::new(anchor, body, 1.0)).unwrap();
+
+        world.step(1.0 / 60.0);
+
+        assert_eq!(world.constraint_force(rope), None);
+    }
+
+    // a rope that only pulls, slack while the bodies are closer than its length
+    struct Tether(Rope);
+
+    impl Constraint for Tether {
+        fn bodies(&self) -> BodyVec<BodyHandle> {
+            self.0.bodies()
+        }
+
+        fn compute_correction(&self, bodies: &BodySet) -> Option<CorrectionData> {
+            if self.0.error(bodies) <= 0.0 { return None; }
+
+            self.0.compute_correction(bodies)
+        }
+    }
+
+    #[test]
+    fn constraint_force_is_none_after_going_slack_during_the_step() {
+        for solver_mode in SOLVER_MODES {
+            let mut world = World::new(Vector3::zeros(), 4, 1);
+
+            world.solver_mode = solver_mode;
+
+            // stretched, the first sub step snaps the body back and it keeps flying towards the anchor
+            let anchor = world.create_body(RigidBodyDesc::fixed());
+            let body = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(0.0, -1.5, 0.0)));
+            let tether = world.add_constraint(Tether(Rope::new(anchor, body, 1.0))).unwrap();
+
+            world.step(1.0 / 60.0);
+
+            assert!(world.bodies.position[*body].y > -1.0, "{solver_mode:?}");
+            assert_eq!(world.constraint_force(tether), None, "{solver_mode:?}");
+        }
+    }
+
+    #[test]
+    fn overloaded_constraints_break_and_emit_an_event() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 8, 4);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let body = world.create_body(RigidBodyDesc::dynamic()
+            .with_position(Point3::new(0.0, -1.0, 0.0))
+            .with_mass(2.0, Matrix3::identity()));
+
+        let rope = world.add_constraint(Rope::new(anchor, body, 1.0)).unwrap();
+        world.set_constraint_user_data(rope, 7);
+        world.set_constraint_break_threshold(rope, Some(BreakThreshold::new(10.0, Precision::INFINITY)));
+
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+
+        let events: Vec<_> = world.drain_events().collect();
+
+        let [Event::JointBroken { constraint, force, user_data }] = events[..] else { panic!("expected one broken joint, got {events:?}"); };
+        assert_eq!(constraint, rope);
+        assert_eq!(user_data, 7);
+        assert!(force.magnitude() > 10.0);
+
+        assert!(!world.is_constraint_enabled(rope));
+        assert!(world.bodies.position[*body].y < -1.5);
+    }
+
+    #[test]
+    fn constraints_below_their_threshold_hold() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 8, 4);
+
+        let anchor = world.create_body(RigidBodyDesc::fixed());
+        let body = world.create_body(RigidBodyDesc::dynamic()
+            .with_position(Point3::new(0.0, -1.0, 0.0))
+            .with_mass(2.0, Matrix3::identity()));
+
+        let rope = world.add_constraint(Rope::new(anchor, body, 1.0)).unwrap();
+        world.set_constraint_break_threshold(rope, Some(BreakThreshold::new(30.0, Precision::INFINITY)));
+
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+
+        assert!(world.events().is_empty());
+        assert!(world.is_constraint_enabled(rope));
+    }
+
+    #[test]
+    fn broken_constraints_stop_counting_towards_their_bodies() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 8, 8);
+
+        world.solver_mode = SolverMode::Jacobi(JacobiAveraging::ConstraintCount);
+
+        let ropes = chain(&mut world, 2);
+        world.set_constraint_break_threshold(ropes[1], Some(BreakThreshold::new(0.1, Precision::INFINITY)));
+
+        world.step(1.0 / 60.0);
+
+        assert!(!world.is_constraint_enabled(ropes[1]));
+
+        let first_link = *world.constraint_ref::<Rope>(ropes[0]).unwrap().body_b;
+        assert_eq!(world.jacobi_solver.body_counts[first_link], 1.0);
+    }
+
+    const SOLVER_MODES: [SolverMode; 4] = [
+        SolverMode::GaussSeidel,
+        SolverMode::Coloured,
+        SolverMode::Jacobi(JacobiAveraging::ConstraintCount),
+        SolverMode::Jacobi(JacobiAveraging::MassSplitting)
+    ];
+
+    // a chain pushed sideways so every link keeps moving
+    fn swinging_chain_hash(solver_mode: SolverMode, links: usize) -> u64 {
+        let mut world = World::new(Vector3::new(3.0, -9.81, 0.0), 4, 2);
+
+        world.solver_mode = solver_mode;
+
+        chain(&mut world, links);
+
+        for _ in 0..10 {
+            world.step(1.0 / 60.0);
+        }
+
+        world.state_hash()
+    }
+
+    #[test]
+    fn identical_runs_hash_the_same() {
+        for solver_mode in SOLVER_MODES {
+            let hash = swinging_chain_hash(solver_mode, 50);
+
+            assert_eq!(hash, swinging_chain_hash(solver_mode, 50), "{solver_mode:?}");
+            assert_ne!(hash, swinging_chain_hash(solver_mode, 51), "{solver_mode:?}");
+        }
+    }
+
+    // long enough for the parallel solvers to split their batches between threads
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_solving_does_not_depend_on_the_thread_count() {
+        let run = |threads: usize, solver_mode: SolverMode| rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| swinging_chain_hash(solver_mode, 1200));
+
+        for solver_mode in SOLVER_MODES {
+            assert_eq!(run(1, solver_mode), run(4, solver_mode), "{solver_mode:?}");
+        }
+    }
+
+    #[test]
+    fn kinematic_bodies_land_exactly_on_their_target() {
+        let mut world = World::new(Vector3::zeros(), 7, 1);
+
+        let body = world.create_body(RigidBodyDesc::kinematic());
+        let position = Point3::new(1.0, 2.0, 3.0);
+        let orientation = UnitQuaternion::from_scaled_axis(Vector3::new(0.5, 0.5, 0.0));
+
+        world.set_kinematic_target(body, position, orientation);
+        world.step(1.0 / 60.0);
+
+        assert_eq!(world.bodies.position(body), Some(position));
+        assert_eq!(world.bodies.orientation(body), Some(orientation));
+        assert!(!world.bodies.is_kinematic_moving(*body));
+    }
+
+    #[test]
+    fn kinematic_velocity_follows_the_target() {
+        let mut world = World::new(Vector3::zeros(), 4, 1);
+
+        let body = world.create_body(RigidBodyDesc::kinematic());
+
+        world.set_kinematic_target(body, Point3::new(0.6, 0.0, 0.0), UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 0.3));
+        world.step(1.0 / 60.0);
+
+        assert!((world.bodies.linear_velocity(body).unwrap() - Vector3::new(36.0, 0.0, 0.0)).norm() < 1.0e-3);
+        assert!((world.bodies.angular_velocity(body).unwrap() - Vector3::new(0.0, 0.0, 18.0)).norm() < 1.0e-3);
+
+        // held at the target
+        world.step(1.0 / 60.0);
+
+        assert_eq!(world.bodies.linear_velocity(body), Some(Vector3::zeros()));
+        assert_eq!(world.bodies.angular_velocity(body), Some(Vector3::zeros()));
+    }
+
+    #[test]
+    fn kinematic_bodies_drag_jointed_bodies_along() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 8, 4);
+
+        let handle = world.create_body(RigidBodyDesc::kinematic());
+        let body = world.create_body(RigidBodyDesc::dynamic()
+            .with_position(Point3::new(0.0, -1.0, 0.0))
+            .with_damping(Damping::new(2.0, 2.0)));
+
+        world.add_constraint(Rope::new(handle, body, 1.0));
+
+        for i in 1..=60 {
+            world.set_kinematic_target(handle, Point3::new(i as Precision * 0.05, 0.0, 0.0), UnitQuaternion::identity());
+            world.step(1.0 / 60.0);
+        }
+
+        for _ in 0..240 {
+            world.step(1.0 / 60.0);
+        }
+
+        assert!((world.bodies.position(body).unwrap() - Point3::new(3.0, -1.0, 0.0)).norm() < 0.1);
+    }
+
+    #[test]
+    fn islands_on_a_rotated_kinematic_body_fall_asleep() {
+        let mut world = World::new(Vector3::new(0.0, -9.81, 0.0), 8, 4);
+
+        world.sleep_settings.enabled = true;
+
+        let handle = world.create_body(RigidBodyDesc::kinematic());
+        let body = world.create_body(RigidBodyDesc::dynamic().with_position(Point3::new(0.0, -1.0, 0.0)));
+
+        world.add_constraint(Rope::new(handle, body, 1.0));
+        world.set_kinematic_target(handle, Point3::origin(), UnitQuaternion::from_axis_angle(&Vector3::z_axis(), 1.3));
+
+        for _ in 0..600 {
+            world.step(1.0 / 60.0);
+        }
+
+        assert!(world.bodies.is_sleeping(*body));
+    }
+}
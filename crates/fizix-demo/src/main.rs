@@ -1,12 +1,13 @@
-use std::f64::consts::{FRAC_PI_2};
 use std::time::Instant;
 use fizix_constraints::{AxisConstraint, DistanceConstraint};
-use fizix_core::{Precision, World};
+use fizix_core::consts::FRAC_PI_2;
+use fizix_core::{Damping, FixedTimestep, Precision, World};
 use kiss3d::light::Light;
 use kiss3d::text::Font;
 use kiss3d::window::{Window};
 use nalgebra::{Matrix3, Point3, UnitQuaternion, Vector3};
 
+#[allow(dead_code)]
 const ORANGE: (f32, f32, f32) = (244.0 / 255.0, 115.9 / 255.0, 51.0 / 255.0); // primary color
 const LIGHT_GRAY: (f32, f32, f32) = (108.0 / 255.0, 112.0 / 255.0, 134.0 / 255.0); // secondary color
 const DARK_GRAY: (f32, f32, f32) = (49.0 / 255.0, 50.0 / 255.0, 68.0 / 255.0); // static color
@@ -16,6 +17,8 @@ fn main() {
     let mut window = Window::new_with_size("Fizix", 1280, 720);
     let mut world = World::new(Vector3::new(0.0, -9.81 * 2.0, 0.0), 16, 2);
 
+    world.default_damping = Damping::new(0.1, 0.1);
+
     window.set_light(Light::StickToCamera);
     window.set_background_color(24.0  / 255.0, 24.0 / 255.0, 37.0 / 255.0);
 
@@ -86,48 +89,58 @@ fn main() {
         ..Default::default()
     });
 
+    let mut timestep = FixedTimestep::new(1.0 / 60.0, 4);
     let mut last_time = Instant::now();
 
     let mut fps_samples = [1.0 / 75.0; 75];
     let mut sample_index = 0;
 
     while window.render() {
-        let elapsed = last_time.elapsed().as_secs_f64();
+        let elapsed = from_f64(last_time.elapsed().as_secs_f64());
 
         last_time = Instant::now();
         fps_samples[sample_index] = elapsed;
         sample_index = (sample_index + 1) % fps_samples.len();
 
-        world.step(elapsed);
+        timestep.advance(&mut world, elapsed);
 
         let fps = fps_samples.len() as Precision / fps_samples.iter().sum::<Precision>();
 
         window.draw_text(
-            &format!("Body Count: {}\nConstraint Count: {}\nFPS: {:.0}", world.bodies.position.len(), world.constraints.len(), fps),
+            &format!("Body Count: {}\nConstraint Count: {}\nFPS: {:.0}", world.bodies().len(), world.constraints().len(), fps),
             &kiss3d::nalgebra::Point2::new(10.0, 10.0),
             42.0,
             &Font::default(),
             &kiss3d::nalgebra::Point3::new(WHITE.0, WHITE.1, WHITE.2),
         );
         
-        for (i, node) in nodes.iter_mut().enumerate() {
-            if i >= world.bodies.position.len() { break; }
-
-            let position = world.bodies.position[i];
-            let orientation = world.bodies.orientation[i];
+        for (node, (_, pose)) in nodes.iter_mut().zip(timestep.interpolated_poses(&world)) {
+            let position = pose.translation.vector;
+            let orientation = pose.rotation;
             let orientation = kiss3d::nalgebra::Quaternion::new(
-                orientation.w as f32,
-                orientation.i as f32,
-                orientation.j as f32,
-                orientation.k as f32
+                to_f32(orientation.w),
+                to_f32(orientation.i),
+                to_f32(orientation.j),
+                to_f32(orientation.k)
             );
 
-            node.set_local_translation(kiss3d::nalgebra::Translation3::new(position.x as f32, position.y as f32, position.z as f32));
+            node.set_local_translation(kiss3d::nalgebra::Translation3::new(to_f32(position.x), to_f32(position.y), to_f32(position.z)));
             node.set_local_rotation(kiss3d::nalgebra::UnitQuaternion::from_quaternion(orientation));
         }
     }
 }
 
+// rendering is always f32 and timing always f64, each cast only does something in one of the builds
+#[allow(clippy::unnecessary_cast)]
+fn to_f32(value: Precision) -> f32 {
+    value as f32
+}
+
+#[allow(clippy::unnecessary_cast)]
+fn from_f64(value: f64) -> Precision {
+    value as Precision
+}
+
 pub fn cuboid_inertia_tensor(width: Precision, height: Precision, length: Precision, mass: Precision) -> Matrix3<Precision> {
     let x2 = width * width;
     let y2 = height * height;